@@ -0,0 +1,280 @@
+use std::{collections::HashMap, path::{Path, PathBuf}};
+
+use anyhow::{anyhow, Context, Result};
+use crossterm::event::KeyCode;
+use serde::Deserialize;
+
+/// Something the timer can be told to do with a key
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Action {
+    Reset,
+    Undo,
+    Skip,
+    SwitchComparison,
+    ToggleTimingSource,
+    SaveSplits,
+    Pause,
+    SwitchSlot,
+    ToggleStats,
+    Help,
+    Quit,
+}
+
+impl Action {
+    /// Every action, in the order they are listed in the help
+    pub const ALL: [Action; 11] = [
+        Self::Reset,
+        Self::Undo,
+        Self::Skip,
+        Self::SwitchComparison,
+        Self::ToggleTimingSource,
+        Self::SaveSplits,
+        Self::Pause,
+        Self::SwitchSlot,
+        Self::ToggleStats,
+        Self::Help,
+        Self::Quit,
+    ];
+
+    /// The name of the action in bindings files
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::Reset => "reset",
+            Self::Undo => "undo",
+            Self::Skip => "skip",
+            Self::SwitchComparison => "comparison",
+            Self::ToggleTimingSource => "timing",
+            Self::SaveSplits => "save",
+            Self::Pause => "pause",
+            Self::SwitchSlot => "slot",
+            Self::ToggleStats => "stats",
+            Self::Help => "help",
+            Self::Quit => "quit",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Reset => "reset the attempt",
+            Self::Undo => "undo the last split",
+            Self::Skip => "skip the next split",
+            Self::SwitchComparison => "switch the comparison",
+            Self::ToggleTimingSource => "switch the timing source",
+            Self::SaveSplits => "save splits now",
+            Self::Pause => "pause or unpause",
+            Self::SwitchSlot => "switch to the next save slot",
+            Self::ToggleStats => "show or hide the sum of best and time saves",
+            Self::Help => "show or hide the key bindings",
+            Self::Quit => "quit",
+        }
+    }
+
+    fn default_keys(&self) -> Vec<KeyCode> {
+        match self {
+            Self::Reset => vec![KeyCode::Char('r')],
+            Self::Undo => vec![KeyCode::Char('u')],
+            Self::Skip => vec![KeyCode::Char('s')],
+            Self::SwitchComparison => vec![KeyCode::Char('c')],
+            Self::ToggleTimingSource => vec![KeyCode::Char('t')],
+            Self::SaveSplits => vec![KeyCode::Char('w')],
+            Self::Pause => vec![KeyCode::Char('p')],
+            Self::SwitchSlot => vec![KeyCode::Char('n')],
+            Self::ToggleStats => vec![KeyCode::Char('i')],
+            Self::Help => vec![KeyCode::Char('h'), KeyCode::Char('?')],
+            Self::Quit => vec![KeyCode::Char('q')],
+        }
+    }
+}
+
+/// Which keys trigger which actions
+#[derive(Clone, Debug)]
+pub struct Bindings {
+    keys: HashMap<Action, Vec<KeyCode>>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self {
+            keys: Action::ALL.iter().map(|action| (*action, action.default_keys())).collect(),
+        }
+    }
+}
+
+/// One or more keys for an action in a bindings file
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Bindings {
+    /// Loads bindings from a TOML file, like `reset = "r"` or `quit = ["q", "esc"]`. Actions that
+    /// are not in the file keep their default keys
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("could not read bindings file {}", path.display()))?;
+        let file: HashMap<String, KeyList> = toml::from_str(&text).with_context(|| format!("could not parse bindings file {}", path.display()))?;
+
+        let mut bindings = Self::default();
+        for (id, keys) in file {
+            let action = *Action::ALL.iter().find(|action| action.id() == id).ok_or_else(|| {
+                let ids: Vec<&str> = Action::ALL.iter().map(Action::id).collect();
+                anyhow!("unknown action '{}' in {}. expected one of: {}", id, path.display(), ids.join(", "))
+            })?;
+            let keys = match keys {
+                KeyList::One(key) => vec![key],
+                KeyList::Many(keys) => keys,
+            };
+            let keys = keys.iter().map(|key| parse_key(key)).collect::<Result<_>>().with_context(|| format!("invalid key for {} in {}", id, path.display()))?;
+            bindings.keys.insert(action, keys);
+        }
+
+        for action in Action::ALL {
+            for other in Action::ALL.iter().filter(|other| **other != action) {
+                if let Some(key) = bindings.keys(action).iter().find(|key| bindings.keys(*other).contains(key)) {
+                    return Err(anyhow!("{} is bound to both {} and {} in {}", key_name(key), action.id(), other.id(), path.display()));
+                }
+            }
+        }
+        Ok(bindings)
+    }
+
+    /// The file bindings are loaded from when none is given, if it exists
+    pub fn default_path() -> Option<PathBuf> {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME").filter(|config_home| !config_home.is_empty()).map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config_home.join("rcas").join("bindings.toml")).filter(|path| path.is_file())
+    }
+
+    /// The action a key is bound to
+    pub fn action(&self, key: KeyCode) -> Option<Action> {
+        Action::ALL.into_iter().find(|action| self.keys(*action).contains(&key))
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.keys.get(&action).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// Parses the name of a key, like "r", "space", "esc" or "f1"
+fn parse_key(name: &str) -> Result<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(KeyCode::Char(c));
+    }
+    let key = match name.to_lowercase().as_str() {
+        "space" => KeyCode::Char(' '),
+        "enter" | "return" => KeyCode::Enter,
+        "esc" | "escape" => KeyCode::Esc,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "delete" | "del" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        lower => match lower.strip_prefix('f').and_then(|number| number.parse().ok()) {
+            Some(number @ 1..=12) => KeyCode::F(number),
+            _ => return Err(anyhow!("unknown key '{}'", name)),
+        },
+    };
+    Ok(key)
+}
+
+/// The name of a key, as it is shown in the help
+pub fn key_name(key: &KeyCode) -> String {
+    match key {
+        KeyCode::Char(' ') => "space".to_owned(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(number) => format!("f{}", number),
+        KeyCode::Enter => "enter".to_owned(),
+        KeyCode::Esc => "esc".to_owned(),
+        KeyCode::Tab => "tab".to_owned(),
+        KeyCode::Backspace => "backspace".to_owned(),
+        KeyCode::Delete => "delete".to_owned(),
+        KeyCode::Insert => "insert".to_owned(),
+        KeyCode::Home => "home".to_owned(),
+        KeyCode::End => "end".to_owned(),
+        KeyCode::PageUp => "pageup".to_owned(),
+        KeyCode::PageDown => "pagedown".to_owned(),
+        KeyCode::Up => "up".to_owned(),
+        KeyCode::Down => "down".to_owned(),
+        KeyCode::Left => "left".to_owned(),
+        KeyCode::Right => "right".to_owned(),
+        other => format!("{:?}", other).to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes a bindings file to a temporary directory and loads it
+    fn load_bindings(file_name: &str, contents: &str) -> Result<Bindings> {
+        let dir = std::env::temp_dir().join(format!("rcas-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(file_name);
+        std::fs::write(&path, contents).unwrap();
+        let bindings = Bindings::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        bindings
+    }
+
+    #[test]
+    fn parses_key_names() {
+        assert_eq!(parse_key("r").unwrap(), KeyCode::Char('r'));
+        assert_eq!(parse_key("?").unwrap(), KeyCode::Char('?'));
+        assert_eq!(parse_key("space").unwrap(), KeyCode::Char(' '));
+        assert_eq!(parse_key("Escape").unwrap(), KeyCode::Esc);
+        assert_eq!(parse_key("PageDown").unwrap(), KeyCode::PageDown);
+        assert_eq!(parse_key("f12").unwrap(), KeyCode::F(12));
+        assert!(parse_key("f13").is_err());
+        assert!(parse_key("f0").is_err());
+        assert!(parse_key("ctrl").is_err());
+        assert!(parse_key("").is_err());
+    }
+
+    #[test]
+    fn key_names_parse_back() {
+        for action in Action::ALL {
+            for key in Bindings::default().keys(action) {
+                assert_eq!(parse_key(&key_name(key)).unwrap(), *key);
+            }
+        }
+        for key in [KeyCode::Char(' '), KeyCode::Enter, KeyCode::Backspace, KeyCode::F(5), KeyCode::Left] {
+            assert_eq!(parse_key(&key_name(&key)).unwrap(), key);
+        }
+    }
+
+    #[test]
+    fn loads_bindings_over_the_defaults() {
+        let bindings = load_bindings("bindings.toml", "reset = \"space\"\nquit = [\"q\", \"esc\"]\n").unwrap();
+        assert_eq!(bindings.keys(Action::Reset), [KeyCode::Char(' ')]);
+        assert_eq!(bindings.action(KeyCode::Char(' ')), Some(Action::Reset));
+        assert_eq!(bindings.action(KeyCode::Char('r')), None);
+        assert_eq!(bindings.action(KeyCode::Esc), Some(Action::Quit));
+        assert_eq!(bindings.action(KeyCode::Char('u')), Some(Action::Undo));
+    }
+
+    #[test]
+    fn rejects_a_key_bound_to_two_actions() {
+        let error = load_bindings("twice.toml", "reset = \"u\"\n").unwrap_err().to_string();
+        assert!(error.starts_with("u is bound to both reset and undo"), "{}", error);
+        let error = load_bindings("twice-in-file.toml", "skip = \"x\"\npause = [\"p\", \"x\"]\n").unwrap_err().to_string();
+        assert!(error.starts_with("x is bound to both"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_unknown_actions_and_keys() {
+        let error = load_bindings("unknown.toml", "jump = \"j\"\n").unwrap_err().to_string();
+        assert!(error.starts_with("unknown action 'jump'"), "{}", error);
+        assert!(error.contains("expected one of: reset, undo"), "{}", error);
+        let error = format!("{:#}", load_bindings("bad-key.toml", "reset = \"hyper\"\n").unwrap_err());
+        assert!(error.contains("invalid key for reset") && error.contains("unknown key 'hyper'"), "{}", error);
+    }
+}
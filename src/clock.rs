@@ -0,0 +1,70 @@
+use std::time::{Duration, Instant};
+
+/// A wall clock for timing an attempt in real time, which can be paused and stopped
+#[derive(Clone, Debug, Default)]
+pub struct Clock {
+    started_at: Option<Instant>,
+    paused_at: Option<Instant>,
+    /// How long the clock has been paused for in total, not counting the current pause
+    paused_for: Duration,
+    /// The time on the clock when it was stopped
+    stopped_at: Option<Duration>,
+}
+
+impl Clock {
+    /// Starts the clock from zero
+    pub fn start(&mut self) {
+        *self = Self {
+            started_at: Some(Instant::now()),
+            ..Self::default()
+        };
+    }
+
+    /// Stops the clock, keeping the time it shows
+    pub fn stop(&mut self) {
+        if self.is_running() {
+            self.stopped_at = self.elapsed();
+        }
+    }
+
+    /// Lets a stopped clock run again, as if it had never been stopped
+    pub fn unstop(&mut self) {
+        self.stopped_at = None;
+    }
+
+    /// Puts the clock back to how it was before it was started
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn pause(&mut self) {
+        if self.is_running() && self.paused_at.is_none() {
+            self.paused_at = Some(Instant::now());
+        }
+    }
+
+    pub fn resume(&mut self) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.paused_for += paused_at.elapsed();
+        }
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.started_at.is_some()
+    }
+
+    /// Whether the clock has been started and not stopped, including while it's paused
+    pub fn is_running(&self) -> bool {
+        self.is_started() && self.stopped_at.is_none()
+    }
+
+    /// The time on the clock, or none if it has not been started
+    pub fn elapsed(&self) -> Option<Duration> {
+        if let Some(stopped_at) = self.stopped_at {
+            return Some(stopped_at);
+        }
+        let started_at = self.started_at?;
+        let now = self.paused_at.unwrap_or_else(Instant::now);
+        Some(now.duration_since(started_at).saturating_sub(self.paused_for))
+    }
+}
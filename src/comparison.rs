@@ -0,0 +1,220 @@
+use std::{collections::HashMap, time::Duration};
+
+use crate::{history::History, levels::{Route, Split}, saves::TimeMap};
+
+/// What the times of the current attempt are compared against
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Comparison {
+    #[default]
+    PersonalBest,
+    /// The best time of every segment
+    BestSegments,
+    /// The average time of every segment in the history
+    Average,
+    /// The median time of every segment in the history
+    Median,
+    /// The most recent attempt in the history
+    LatestRun,
+    /// The PB, with its total spread over the segments according to how fast they can be
+    BalancedPb,
+}
+
+impl Comparison {
+    /// Every comparison, in the order they are switched through
+    pub const ALL: [Comparison; 6] = [
+        Self::PersonalBest,
+        Self::BestSegments,
+        Self::Average,
+        Self::Median,
+        Self::LatestRun,
+        Self::BalancedPb,
+    ];
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::PersonalBest => "PB",
+            Self::BestSegments => "Best Segments",
+            Self::Average => "Average",
+            Self::Median => "Median",
+            Self::LatestRun => "Latest Run",
+            Self::BalancedPb => "Balanced PB",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|comparison| comparison == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// The time of each segment of the route to compare against. Segments that have no time to
+    /// compare against are left out
+    pub fn times(&self, route: &Route, pb: &TimeMap, best_splits: &TimeMap, history: &History) -> TimeMap {
+        match self {
+            Self::PersonalBest => pb.clone(),
+            Self::BestSegments => best_splits.clone(),
+            Self::Average => segment_times(history).into_iter()
+                .map(|(split, times)| {
+                    let total: Duration = times.iter().sum();
+                    (split, total / times.len() as u32)
+                })
+                .collect(),
+            Self::Median => segment_times(history).into_iter()
+                .map(|(split, mut times)| {
+                    times.sort();
+                    let middle = times.len() / 2;
+                    let median = if times.len() % 2 == 0 {
+                        (times[middle - 1] + times[middle]) / 2
+                    } else {
+                        times[middle]
+                    };
+                    (split, median)
+                })
+                .collect(),
+            Self::LatestRun => history.attempts.last().map_or_else(HashMap::new, |attempt| {
+                attempt.splits.iter()
+                    .filter_map(|attempt_split| Some((attempt_split.split.clone(), attempt_split.time?)))
+                    .collect()
+            }),
+            Self::BalancedPb => balanced_pb(route, pb, best_splits),
+        }
+    }
+}
+
+/// Every time each segment was done in the history. Skipped segments are left out
+fn segment_times(history: &History) -> HashMap<Split, Vec<Duration>> {
+    let mut times: HashMap<Split, Vec<Duration>> = HashMap::new();
+    for attempt_split in history.attempts.iter().flat_map(|attempt| &attempt.splits) {
+        if let (Some(time), false) = (attempt_split.time, attempt_split.skipped) {
+            times.entry(attempt_split.split.clone()).or_default().push(time);
+        }
+    }
+    times
+}
+
+/// Spreads the time of the PB over its segments in proportion to their best times, so that every
+/// segment is equally hard to beat. Segments without a best time keep their PB time
+fn balanced_pb(route: &Route, pb: &TimeMap, best_splits: &TimeMap) -> TimeMap {
+    let balanced: Vec<&Split> = route.splits.iter()
+        .map(|route_split| &route_split.split)
+        .filter(|split| pb.contains_key(*split) && best_splits.get(*split).is_some_and(|time| !time.is_zero()))
+        .collect();
+    let pb_total: Duration = balanced.iter().map(|split| pb[*split]).sum();
+    let best_total: Duration = balanced.iter().map(|split| best_splits[*split]).sum();
+
+    let mut times = pb.clone();
+    if best_total.is_zero() {
+        return times;
+    }
+    for split in balanced {
+        times.insert(split.clone(), pb_total.mul_f64(best_splits[split].as_secs_f64() / best_total.as_secs_f64()));
+    }
+    times
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, time::SystemTime};
+
+    use super::*;
+    use crate::{history::{Attempt, AttemptSplit}, levels::{Chapter, Side}};
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    fn route() -> Route {
+        Route::new("test", vec![Chapter::City(Side::A), Chapter::Site(Side::A)])
+    }
+
+    fn city() -> Split {
+        Split::Chapter(Chapter::City(Side::A))
+    }
+
+    fn site() -> Split {
+        Split::Chapter(Chapter::Site(Side::A))
+    }
+
+    /// A history with an attempt for each pair of segment times. A missing time is a split that
+    /// was not reached
+    fn history(attempts: &[(Option<u64>, Option<u64>)]) -> History {
+        let mut history = History::empty(Path::new("history.jsonl"));
+        for (city_time, site_time) in attempts {
+            history.attempts.push(Attempt {
+                started: SystemTime::UNIX_EPOCH,
+                route: "test".to_owned(),
+                splits: vec![
+                    AttemptSplit { split: city(), time: city_time.map(secs), rta: None, skipped: false },
+                    AttemptSplit { split: site(), time: site_time.map(secs), rta: None, skipped: false },
+                ],
+                deaths: 0,
+                finished: site_time.is_some(),
+                reset_at: None,
+            });
+        }
+        history
+    }
+
+    fn times(comparison: Comparison, pb: &TimeMap, best_splits: &TimeMap, history: &History) -> TimeMap {
+        comparison.times(&route(), pb, best_splits, history)
+    }
+
+    #[test]
+    fn next_cycles_through_every_comparison() {
+        let mut comparison = Comparison::default();
+        for expected in Comparison::ALL.iter().skip(1).chain(Comparison::ALL.first()) {
+            comparison = comparison.next();
+            assert_eq!(comparison, *expected);
+        }
+    }
+
+    #[test]
+    fn average_of_reached_segments() {
+        let history = history(&[(Some(10), Some(20)), (Some(20), None), (Some(30), Some(40))]);
+        let times = times(Comparison::Average, &TimeMap::new(), &TimeMap::new(), &history);
+        assert_eq!(times, TimeMap::from([(city(), secs(20)), (site(), secs(30))]));
+    }
+
+    #[test]
+    fn median_of_odd_and_even_counts() {
+        let history = history(&[(Some(10), Some(20)), (Some(50), None), (Some(20), Some(40))]);
+        let times = times(Comparison::Median, &TimeMap::new(), &TimeMap::new(), &history);
+        assert_eq!(times, TimeMap::from([(city(), secs(20)), (site(), secs(30))]));
+    }
+
+    #[test]
+    fn skipped_segments_are_left_out() {
+        let mut history = history(&[(Some(10), Some(20)), (Some(30), Some(1))]);
+        history.attempts[1].splits[1].skipped = true;
+        let times = times(Comparison::Average, &TimeMap::new(), &TimeMap::new(), &history);
+        assert_eq!(times[&site()], secs(20));
+    }
+
+    #[test]
+    fn latest_run_uses_the_last_attempt() {
+        let history = history(&[(Some(10), Some(20)), (Some(30), None)]);
+        let times = times(Comparison::LatestRun, &TimeMap::new(), &TimeMap::new(), &history);
+        assert_eq!(times, TimeMap::from([(city(), secs(30))]));
+    }
+
+    #[test]
+    fn latest_run_is_empty_without_history() {
+        assert!(times(Comparison::LatestRun, &TimeMap::new(), &TimeMap::new(), &history(&[])).is_empty());
+    }
+
+    #[test]
+    fn balanced_pb_spreads_the_pb_by_best_splits() {
+        let pb = TimeMap::from([(city(), secs(50)), (site(), secs(70))]);
+        let best_splits = TimeMap::from([(city(), secs(20)), (site(), secs(60))]);
+        let times = balanced_pb(&route(), &pb, &best_splits);
+        assert_eq!(times, TimeMap::from([(city(), secs(30)), (site(), secs(90))]));
+        assert_eq!(times.values().sum::<Duration>(), secs(120));
+    }
+
+    #[test]
+    fn balanced_pb_keeps_segments_without_a_best_split() {
+        let pb = TimeMap::from([(city(), secs(50)), (site(), secs(70))]);
+        let best_splits = TimeMap::from([(city(), secs(20)), (site(), Duration::ZERO)]);
+        assert_eq!(balanced_pb(&route(), &pb, &best_splits), pb);
+        assert_eq!(balanced_pb(&route(), &pb, &TimeMap::new()), pb);
+    }
+}
@@ -0,0 +1,111 @@
+use std::{path::{Path, PathBuf}, time::Duration};
+
+use anyhow::{anyhow, Result};
+
+use crate::{levels::{Chapter, Route}, saves::TimingSource, watch::WatchOptions, bindings::Bindings};
+
+const USAGE: &str = "usage: rcas [--route <name or file>] [--timing <played|best|fullclear|file>] [--slot <number>] [--save-dir <path>] [--poll] [--poll-interval <ms>] [--record <file>] [--replay <file>] [--replay-speed <factor>] [--bindings <file>] [--stats]";
+
+pub struct Config {
+    pub route: Route,
+    pub timing_source: TimingSource,
+    /// The save slot to watch. If this is not set, the most recently modified save is watched
+    pub save_slot: Option<u32>,
+    /// The directory the saves are in. If this is not set, it is searched for
+    pub save_dir: Option<PathBuf>,
+    pub watch: WatchOptions,
+    /// The file to record every save update to
+    pub record: Option<PathBuf>,
+    /// A recording to replay instead of watching the save
+    pub replay: Option<PathBuf>,
+    /// How many times faster than it was recorded the recording is replayed
+    pub replay_speed: f64,
+    pub bindings: Bindings,
+    /// Whether the sum of best, best possible time and possible time saves are shown from the start
+    pub show_stats: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            route: Route::any_percent(),
+            timing_source: TimingSource::default(),
+            save_slot: None,
+            save_dir: None,
+            watch: WatchOptions::default(),
+            record: None,
+            replay: None,
+            replay_speed: 1.0,
+            bindings: Bindings::default(),
+            show_stats: false,
+        }
+    }
+}
+
+impl Config {
+    pub fn from_args() -> Result<Self> {
+        let mut config = Self::default();
+        if let Some(path) = Bindings::default_path() {
+            config.bindings = Bindings::load(&path)?;
+        }
+        let mut args = std::env::args().skip(1);
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--route" => {
+                    let name = args.next().ok_or(anyhow!("missing value for --route\n{}", USAGE))?;
+                    // Files are checked before chapters, since a path with a slash in it also parses
+                    // as the SID of a modded chapter
+                    config.route = if let Some(route) = Route::builtin(&name) {
+                        route
+                    } else if Path::new(&name).is_file() {
+                        Route::load(Path::new(&name))?
+                    } else if let Ok(chapter) = Chapter::parse_lenient(&name) {
+                        Route::individual_level(chapter)
+                    } else {
+                        let names: Vec<String> = Route::builtins().into_iter().map(|route| route.name).collect();
+                        return Err(anyhow!("unknown route '{}'. expected a route file, a chapter or one of: {}", name, names.join(", ")));
+                    };
+                }
+                "--timing" => {
+                    let source = args.next().ok_or(anyhow!("missing value for --timing\n{}", USAGE))?;
+                    config.timing_source = TimingSource::try_from(source)?;
+                }
+                "--slot" => {
+                    let slot = args.next().ok_or(anyhow!("missing value for --slot\n{}", USAGE))?;
+                    config.save_slot = Some(slot.parse().map_err(|_| anyhow!("invalid save slot '{}'. expected the number of a save file", slot))?);
+                }
+                "--save-dir" => {
+                    let save_dir = args.next().ok_or(anyhow!("missing value for --save-dir\n{}", USAGE))?;
+                    config.save_dir = Some(PathBuf::from(save_dir));
+                }
+                "--poll" => config.watch.force_polling = true,
+                "--poll-interval" => {
+                    let interval = args.next().ok_or(anyhow!("missing value for --poll-interval\n{}", USAGE))?;
+                    let millis = interval.parse().map_err(|_| anyhow!("invalid poll interval '{}'. expected a number of milliseconds", interval))?;
+                    config.watch.poll_interval = Duration::from_millis(millis);
+                }
+                "--record" => {
+                    let path = args.next().ok_or(anyhow!("missing value for --record\n{}", USAGE))?;
+                    config.record = Some(PathBuf::from(path));
+                }
+                "--replay" => {
+                    let path = args.next().ok_or(anyhow!("missing value for --replay\n{}", USAGE))?;
+                    config.replay = Some(PathBuf::from(path));
+                }
+                "--replay-speed" => {
+                    let speed = args.next().ok_or(anyhow!("missing value for --replay-speed\n{}", USAGE))?;
+                    config.replay_speed = speed.parse().ok().filter(|speed| *speed > 0.0).ok_or(anyhow!("invalid replay speed '{}'. expected a number above 0", speed))?;
+                }
+                "--bindings" => {
+                    let path = args.next().ok_or(anyhow!("missing value for --bindings\n{}", USAGE))?;
+                    config.bindings = Bindings::load(Path::new(&path))?;
+                }
+                "--stats" => config.show_stats = true,
+                _ => return Err(anyhow!("unknown argument '{}'\n{}", arg, USAGE)),
+            }
+        }
+
+        Ok(config)
+    }
+}
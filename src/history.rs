@@ -0,0 +1,62 @@
+use std::{fs::OpenOptions, path::{Path, PathBuf}, time::{Duration, SystemTime}};
+
+use anyhow::{Context, Result};
+use serde::{Serialize, Deserialize};
+
+use crate::{jsonl, levels::Split};
+
+/// A split of an attempt, with the time of its segment
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AttemptSplit {
+    pub split: Split,
+    /// The in-game time of the segment, or none if the split was not reached
+    pub time: Option<Duration>,
+    /// The real time of the segment, if it is known
+    pub rta: Option<Duration>,
+    #[serde(default)]
+    pub skipped: bool,
+}
+
+/// An attempt at a route, which either finished or was reset
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Attempt {
+    pub started: SystemTime,
+    pub route: String,
+    /// Every split of the route, in order
+    pub splits: Vec<AttemptSplit>,
+    pub deaths: u32,
+    pub finished: bool,
+    /// The first split that was not reached when the attempt was reset
+    pub reset_at: Option<Split>,
+}
+
+/// Every attempt at a route, stored in a file with one JSON object per line. Attempts are only
+/// ever appended to the file
+pub struct History {
+    path: PathBuf,
+    pub attempts: Vec<Attempt>,
+}
+
+impl History {
+    /// Loads the history from a file. A file that does not exist is an empty history
+    pub fn load(path: &Path) -> Result<Self> {
+        let attempts = match std::fs::read_to_string(path) {
+            Ok(text) => jsonl::parse(&text, path, "attempt")?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e).with_context(|| format!("could not open history {}", path.display())),
+        };
+        Ok(Self { path: path.to_owned(), attempts })
+    }
+
+    /// A history that is written to a file, but does not have any attempts yet
+    pub fn empty(path: &Path) -> Self {
+        Self { path: path.to_owned(), attempts: Vec::new() }
+    }
+
+    pub fn append(&mut self, attempt: Attempt) -> Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path).with_context(|| format!("could not open history {}", self.path.display()))?;
+        jsonl::write_line(&mut file, &attempt)?;
+        self.attempts.push(attempt);
+        Ok(())
+    }
+}
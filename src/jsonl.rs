@@ -0,0 +1,56 @@
+use std::{io::Write, path::Path};
+
+use anyhow::{Context, Result};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Parses a file with one JSON object per line, read from `path`. Blank lines are skipped. A last
+/// line without a line break was cut off while it was being written, and is left out if it can't
+/// be parsed. `what` is what a line holds, for the error messages
+pub fn parse<T: DeserializeOwned>(text: &str, path: &Path, what: &str) -> Result<Vec<T>> {
+    let complete = text.ends_with('\n');
+    let line_count = text.lines().count();
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(i, line)| match serde_json::from_str(line) {
+            Err(_) if i + 1 == line_count && !complete => None,
+            result => Some(result.with_context(|| format!("invalid {} on line {} of {}", what, i + 1, path.display()))),
+        })
+        .collect()
+}
+
+/// Writes a value as a single line. The line is written at once, so that a crash can't leave half
+/// of it in the file
+pub fn write_line<T: Serialize>(writer: &mut impl Write, value: &T) -> Result<()> {
+    let mut line = serde_json::to_string(value)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_lines() {
+        let mut text = Vec::new();
+        write_line(&mut text, &1).unwrap();
+        write_line(&mut text, &2).unwrap();
+        let values: Vec<u32> = parse(&String::from_utf8(text).unwrap(), Path::new("test.jsonl"), "number").unwrap();
+        assert_eq!(values, [1, 2]);
+    }
+
+    #[test]
+    fn skips_a_cut_off_last_line() {
+        let values: Vec<Vec<u32>> = parse("[1]\n\n[2, 3]\n[4, ", Path::new("test.jsonl"), "list").unwrap();
+        assert_eq!(values, [vec![1], vec![2, 3]]);
+    }
+
+    #[test]
+    fn fails_on_an_invalid_complete_line() {
+        let error = parse::<u32>("1\nx\n2\n", Path::new("test.jsonl"), "number").unwrap_err();
+        assert_eq!(error.to_string(), "invalid number on line 2 of test.jsonl");
+        assert!(parse::<u32>("1\nx\n", Path::new("test.jsonl"), "number").is_err());
+    }
+}
@@ -1,37 +1,165 @@
-use anyhow::anyhow;
-use serde::{Serialize, Deserialize};
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use serde::{Serialize, Deserialize, Deserializer, de::{self, Visitor, MapAccess, value::MapAccessDeserializer}};
 
 #[derive(Hash, Eq, PartialEq, Debug, Clone, Serialize, Deserialize)]
 #[serde(try_from = "String", into = "String")]
 pub enum Chapter {
-    Prologue, City(Side), Site(Side), Resort(Side), Ridge(Side), Temple(Side), Reflection(Side), Summit(Side), Epilogue, Core(Side), Farewell
+    Prologue, City(Side), Site(Side), Resort(Side), Ridge(Side), Temple(Side), Reflection(Side), Summit(Side), Epilogue, Core(Side), Farewell,
+    /// A chapter from an Everest level set, identified by its SID, i.e.
+    /// "StrawberryJam2021/1-Beginner/maya"
+    Custom(String, Side),
 }
 
+/// The SIDs Everest uses for the vanilla chapters, in order of their index
+const VANILLA_SIDS: [&str; 11] = [
+    "Celeste/0-Intro",
+    "Celeste/1-ForsakenCity",
+    "Celeste/2-OldSite",
+    "Celeste/3-CelestialResort",
+    "Celeste/4-GoldenRidge",
+    "Celeste/5-MirrorTemple",
+    "Celeste/6-Reflection",
+    "Celeste/7-Summit",
+    "Celeste/8-Epilogue",
+    "Celeste/9-Core",
+    "Celeste/LostLevels",
+];
+
 impl Chapter {
+    /// Finds the chapter with the given Everest SID. SIDs of the vanilla chapters give the
+    /// corresponding vanilla chapter, and every other SID gives a custom chapter
+    pub fn from_sid(sid: &str, side: Side) -> anyhow::Result<Self> {
+        match VANILLA_SIDS.iter().position(|vanilla_sid| *vanilla_sid == sid) {
+            Some(index) => Self::from_index(index as u8, side),
+            None => Ok(Self::Custom(sid.to_owned(), side)),
+        }
+    }
+
+    /// The name of the chapter to show to the user. For custom chapters this leaves out the
+    /// level set and lobby part of the SID
+    pub fn display_name(&self) -> String {
+        match self {
+            Self::Custom(_, side) => format!("{} {}", self.short_name(), side),
+            _ => self.to_string(),
+        }
+    }
+
+    /// Gets the vanilla chapter with the given index and side. Fails if the chapter does not
+    /// have that side
     pub fn from_index(index: u8, side: Side) -> anyhow::Result<Self> {
-        match index {
-            0 => Ok(Self::Prologue),
-            1 => Ok(Self::City(side)),
-            2 => Ok(Self::Site(side)),
-            3 => Ok(Self::Resort(side)),
-            4 => Ok(Self::Ridge(side)),
-            5 => Ok(Self::Temple(side)),
-            6 => Ok(Self::Reflection(side)),
-            7 => Ok(Self::Summit(side)),
-            8 => Ok(Self::Epilogue),
-            9 => Ok(Self::Core(side)),
-            10 => Ok(Self::Farewell),
-            _ => Err(anyhow!("Invalid chapter index: {}", index)),
+        let chapter = match index {
+            0 => Self::Prologue,
+            1 => Self::City(side.clone()),
+            2 => Self::Site(side.clone()),
+            3 => Self::Resort(side.clone()),
+            4 => Self::Ridge(side.clone()),
+            5 => Self::Temple(side.clone()),
+            6 => Self::Reflection(side.clone()),
+            7 => Self::Summit(side.clone()),
+            8 => Self::Epilogue,
+            9 => Self::Core(side.clone()),
+            10 => Self::Farewell,
+            _ => return Err(anyhow!("Invalid chapter index: {}", index)),
+        };
+        if chapter.side() != side {
+            return Err(anyhow!("{} does not have a {}-side", chapter.long_name(), side));
         }
+        Ok(chapter)
     }
-    
-    pub fn to_string(&self) -> String {
+
+    /// Every valid vanilla chapter, in the order they appear in the game
+    pub fn all() -> Vec<Self> {
+        (0..VANILLA_SIDS.len() as u8)
+            .flat_map(|index| [Side::A, Side::B, Side::C].into_iter().filter_map(move |side| Self::from_index(index, side).ok()))
+            .collect()
+    }
+
+    /// Parses a chapter the way a person would write it. Case, spaces, punctuation and a
+    /// trailing "side" are ignored, and chapters can be referred to by their number, their name
+    /// or the last word of their name, i.e. "1a", "City A", "Forsaken City B-Side" or "core c".
+    /// Leaving out the side gives the A-side. If nothing matches, the error suggests the closest
+    /// chapter
+    pub fn parse_lenient(value: &str) -> anyhow::Result<Self> {
+        if value.contains('/') {
+            return Self::try_from(value.to_owned());
+        }
+        let mut key = normalize_name(value);
+        if ["aside", "bside", "cside"].iter().any(|side| key.ends_with(side)) {
+            key.truncate(key.len() - "side".len());
+        }
+
+        let aliases = Self::aliases();
+        if let Some((_, index, side)) = aliases.iter().find(|(alias, ..)| *alias == key) {
+            return Self::from_index(*index, side.clone());
+        }
+
+        let suggestion = aliases.iter()
+            .filter_map(|(alias, index, side)| Some((edit_distance(alias, &key), Self::from_index(*index, side.clone()).ok()?)))
+            .min_by_key(|(distance, _)| *distance)
+            .filter(|(distance, _)| *distance <= (key.len() / 3).max(1));
+        match suggestion {
+            Some((_, chapter)) => Err(anyhow!("unknown chapter '{}'. did you mean {} ({})?", value, chapter, chapter.full_name())),
+            None => Err(anyhow!("unknown chapter '{}'", value)),
+        }
+    }
+
+    /// Every name the lenient parser accepts for the vanilla chapters, along with the index and
+    /// side they refer to. Sides that don't exist are included too, so that they give a clear
+    /// error instead of a suggestion
+    fn aliases() -> Vec<(String, u8, Side)> {
+        let mut aliases = Vec::new();
+        for index in 0..VANILLA_SIDS.len() as u8 {
+            let chapter = Self::from_index(index, Side::A).unwrap();
+            let long_name = chapter.long_name();
+            let last_word = long_name.rsplit(' ').next().unwrap_or(long_name);
+            for name in [chapter.short_name(), long_name, last_word] {
+                let name = normalize_name(name);
+                for side in [Side::A, Side::B, Side::C] {
+                    aliases.push((name.clone() + &side.to_string().to_ascii_lowercase(), index, side));
+                }
+                aliases.push((name, index, Side::A));
+            }
+        }
+        aliases
+    }
+
+    /// The full name of the chapter including the side, i.e. "Forsaken City B-Side"
+    pub fn full_name(&self) -> String {
         match self {
             Self::Prologue | Self::Epilogue | Self::Farewell => self.long_name().to_owned(),
-            chapter @ (Self::City(side) | Self::Site(side) | Self::Resort(side) | Self::Ridge(side) | Self::Temple(side) | Self::Reflection(side) | Self::Summit(side) | Self::Core(side)) => chapter.short_name().to_owned() + side.to_string(),
+            Self::Custom(..) => self.display_name(),
+            _ => format!("{} {}-Side", self.long_name(), self.side()),
+        }
+    }
+
+    /// The index of the chapter in the game. Custom chapters do not have an index
+    pub fn index(&self) -> Option<u8> {
+        match self {
+            Self::Prologue => Some(0),
+            Self::City(_) => Some(1),
+            Self::Site(_) => Some(2),
+            Self::Resort(_) => Some(3),
+            Self::Ridge(_) => Some(4),
+            Self::Temple(_) => Some(5),
+            Self::Reflection(_) => Some(6),
+            Self::Summit(_) => Some(7),
+            Self::Epilogue => Some(8),
+            Self::Core(_) => Some(9),
+            Self::Farewell => Some(10),
+            Self::Custom(..) => None,
         }
     }
 
+    /// The side of the chapter. Chapters that only have a single side are A-sides
+    pub fn side(&self) -> Side {
+        match self {
+            Self::Prologue | Self::Epilogue | Self::Farewell => Side::A,
+            Self::City(side) | Self::Site(side) | Self::Resort(side) | Self::Ridge(side) | Self::Temple(side) | Self::Reflection(side) | Self::Summit(side) | Self::Core(side) | Self::Custom(_, side) => side.clone(),
+        }
+    }
+    
     pub fn short_name(&self) -> &str {
         match self {
             Self::Prologue => "Prologue",
@@ -44,10 +172,41 @@ impl Chapter {
             Self::Summit(_) => "7",
             Self::Epilogue => "Epilogue",
             Self::Core(_) => "8",
-            Self::Farewell => "9"
+            Self::Farewell => "9",
+            Self::Custom(sid, _) => sid.rsplit('/').next().unwrap_or(sid),
         }
     }
 
+    /// The checkpoints of the chapter, in the order they are reached
+    pub fn checkpoints(&self) -> &'static [Checkpoint] {
+        match self {
+            Self::City(Side::A) => &CITY_A_CHECKPOINTS,
+            Self::City(Side::B) => &CITY_B_CHECKPOINTS,
+            Self::Site(Side::A) => &SITE_A_CHECKPOINTS,
+            Self::Site(Side::B) => &SITE_B_CHECKPOINTS,
+            Self::Resort(Side::A) => &RESORT_A_CHECKPOINTS,
+            Self::Resort(Side::B) => &RESORT_B_CHECKPOINTS,
+            Self::Ridge(Side::A) => &RIDGE_A_CHECKPOINTS,
+            Self::Ridge(Side::B) => &RIDGE_B_CHECKPOINTS,
+            Self::Temple(Side::A) => &TEMPLE_A_CHECKPOINTS,
+            Self::Temple(Side::B) => &TEMPLE_B_CHECKPOINTS,
+            Self::Reflection(Side::A) => &REFLECTION_A_CHECKPOINTS,
+            Self::Reflection(Side::B) => &REFLECTION_B_CHECKPOINTS,
+            Self::Summit(Side::A) => &SUMMIT_A_CHECKPOINTS,
+            Self::Summit(Side::B) => &SUMMIT_B_CHECKPOINTS,
+            Self::Core(Side::A) => &CORE_A_CHECKPOINTS,
+            Self::Core(Side::B) => &CORE_B_CHECKPOINTS,
+            Self::Farewell => &FAREWELL_CHECKPOINTS,
+            _ => &[],
+        }
+    }
+
+    /// Finds a checkpoint of the chapter by either its name or its room
+    pub fn checkpoint(&self, name_or_room: &str) -> Option<&'static Checkpoint> {
+        let name = normalize_name(name_or_room);
+        self.checkpoints().iter().find(|checkpoint| checkpoint.room == name_or_room || normalize_name(checkpoint.name) == name)
+    }
+
     pub fn long_name(&self) -> &str {
         match self {
             Self::Prologue => "Prologue",
@@ -60,40 +219,54 @@ impl Chapter {
             Self::Summit(_) => "The Summit",
             Self::Epilogue => "Epilogue",
             Self::Core(_) => "Core",
-            Self::Farewell => "Farewell"
+            Self::Farewell => "Farewell",
+            Self::Custom(sid, _) => sid,
         }
     }
 }
 
 impl std::fmt::Display for Chapter {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&self.to_string())
+        match self {
+            Self::Prologue | Self::Epilogue | Self::Farewell => f.write_str(self.long_name()),
+            chapter @ (Self::City(side) | Self::Site(side) | Self::Resort(side) | Self::Ridge(side) | Self::Temple(side) | Self::Reflection(side) | Self::Summit(side) | Self::Core(side)) => write!(f, "{}{}", chapter.short_name(), side),
+            Self::Custom(sid, side) => write!(f, "{}:{}", sid, side),
+        }
     }
 }
 
 impl TryFrom<String> for Chapter {
     type Error = anyhow::Error;
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        match value.as_str() {
-            "Prologue" => Ok(Self::Prologue),
-            "Epilogue" => Ok(Self::Epilogue),
-            "Farewell" => Ok(Self::Farewell),
-            s => {
-                if s.len() != 2 {
-                    return Err(anyhow!("could not convert string '{}' into a chapter", value));
-                }
-                let mut chapter_num = [0; 4];
-                let chapter_num = str::parse(s.chars().nth(0).unwrap().encode_utf8(&mut chapter_num))?;
-                let side = Side::try_from(s.chars().nth(1).unwrap().to_string())?;
-                Chapter::from_index(chapter_num, side)
+        // Only SIDs contain slashes. The side is optional, and defaults to the A-side
+        if value.contains('/') {
+            return match value.rsplit_once(':') {
+                Some((sid, side)) => Chapter::from_sid(sid, Side::try_from(side.to_owned())?),
+                None => Chapter::from_sid(&value, Side::A),
+            };
+        }
+        if let Some(chapter) = Self::all().into_iter().find(|chapter| chapter.to_string() == value) {
+            return Ok(chapter);
+        }
+
+        // The string is not a chapter. If it is a chapter followed by a side, that side is either
+        // invalid or does not exist for the chapter, and the error should say so
+        let trimmed = value.trim_end();
+        if let Some((split_at, _)) = trimmed.char_indices().last() {
+            let (name, side) = trimmed.split_at(split_at);
+            let name = name.trim_end();
+            if let Some(chapter) = Self::all().into_iter().find(|chapter| chapter.short_name() == name || chapter.long_name() == name) {
+                let side = Side::try_from(side.to_owned())?;
+                return Self::from_index(chapter.index().unwrap(), side);
             }
         }
+        Err(anyhow!("could not convert string '{}' into a chapter", value))
     }
 }
 
-impl Into<String> for Chapter {
-    fn into(self) -> String {
-        self.to_string()
+impl From<Chapter> for String {
+    fn from(chapter: Chapter) -> Self {
+        chapter.to_string()
     }
 }
 
@@ -122,7 +295,7 @@ impl Side {
 
 impl std::fmt::Display for Side {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&self.to_string())
+        f.write_str(self.to_string())
     }
 }
 
@@ -139,6 +312,130 @@ impl TryFrom<String> for Side {
     }
 }
 
+/// A checkpoint inside of a chapter. The room is the name of the level the checkpoint is placed
+/// in, which is how checkpoints are stored in the save file
+#[derive(Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub name: &'static str,
+    pub room: &'static str,
+}
+
+const fn checkpoint(name: &'static str, room: &'static str) -> Checkpoint {
+    Checkpoint { name, room }
+}
+
+// The start of a chapter is also a checkpoint in game, but it is left out here since it is
+// reached as soon as the chapter is entered. C-sides, the prologue and the epilogue do not have
+// any checkpoints
+const CITY_A_CHECKPOINTS: [Checkpoint; 2] = [checkpoint("Crossing", "6"), checkpoint("Chasm", "9b")];
+const CITY_B_CHECKPOINTS: [Checkpoint; 2] = [checkpoint("Contraption", "04"), checkpoint("Scrap Pit", "08")];
+const SITE_A_CHECKPOINTS: [Checkpoint; 2] = [checkpoint("Intervention", "3"), checkpoint("Awake", "end_3")];
+const SITE_B_CHECKPOINTS: [Checkpoint; 2] = [checkpoint("Combination Lock", "03"), checkpoint("Dream Altar", "08b")];
+const RESORT_A_CHECKPOINTS: [Checkpoint; 3] = [checkpoint("Huge Mess", "08-a"), checkpoint("Elevator Shaft", "09-d"), checkpoint("Presidential Suite", "00-d")];
+const RESORT_B_CHECKPOINTS: [Checkpoint; 3] = [checkpoint("Staff Quarters", "06"), checkpoint("Library", "11"), checkpoint("Rooftop", "16")];
+const RIDGE_A_CHECKPOINTS: [Checkpoint; 3] = [checkpoint("Shrine", "b-00"), checkpoint("Old Trail", "c-00"), checkpoint("Cliff Face", "d-00")];
+const RIDGE_B_CHECKPOINTS: [Checkpoint; 3] = [checkpoint("Stepping Stones", "b-00"), checkpoint("Gusty Canyon", "c-00"), checkpoint("Eye of the Storm", "d-00")];
+const TEMPLE_A_CHECKPOINTS: [Checkpoint; 4] = [checkpoint("Depths", "b-00"), checkpoint("Unravelling", "c-00"), checkpoint("Search", "d-00"), checkpoint("Rescue", "e-00")];
+const TEMPLE_B_CHECKPOINTS: [Checkpoint; 3] = [checkpoint("Central Chamber", "b-00"), checkpoint("Through the Mirror", "c-00"), checkpoint("Mix Master", "d-00")];
+const REFLECTION_A_CHECKPOINTS: [Checkpoint; 5] = [checkpoint("Lake", "00"), checkpoint("Hollows", "04"), checkpoint("Reflection", "b-00"), checkpoint("Rock Bottom", "boss-00"), checkpoint("Resolution", "after-00")];
+const REFLECTION_B_CHECKPOINTS: [Checkpoint; 3] = [checkpoint("Reflection", "b-00"), checkpoint("Rock Bottom", "c-00"), checkpoint("Reprise", "d-00")];
+const SUMMIT_A_CHECKPOINTS: [Checkpoint; 6] = [checkpoint("500 M", "b-00"), checkpoint("1000 M", "c-00"), checkpoint("1500 M", "d-00"), checkpoint("2000 M", "e-00b"), checkpoint("2500 M", "f-00"), checkpoint("3000 M", "g-00")];
+const SUMMIT_B_CHECKPOINTS: [Checkpoint; 6] = [checkpoint("500 M", "b-00"), checkpoint("1000 M", "c-00"), checkpoint("1500 M", "d-00"), checkpoint("2000 M", "e-00"), checkpoint("2500 M", "f-00"), checkpoint("3000 M", "g-00")];
+const CORE_A_CHECKPOINTS: [Checkpoint; 3] = [checkpoint("Into the Core", "a-00"), checkpoint("Hot and Cold", "c-00"), checkpoint("Heart of the Mountain", "d-00")];
+const CORE_B_CHECKPOINTS: [Checkpoint; 3] = [checkpoint("Into the Core", "b-00"), checkpoint("Burning or Freezing", "c-00"), checkpoint("Heartbeat", "d-00")];
+const FAREWELL_CHECKPOINTS: [Checkpoint; 8] = [
+    checkpoint("Singular", "a-00"),
+    checkpoint("Power Source", "c-00"),
+    checkpoint("Remembered", "e-00z"),
+    checkpoint("Event Horizon", "f-door"),
+    checkpoint("Determination", "h-00b"),
+    checkpoint("Stubbornness", "i-00"),
+    checkpoint("Reconciliation", "j-00"),
+    checkpoint("Farewell", "j-16"),
+];
+
+/// A point in a run where a split can happen. This is either the end of a chapter, or reaching
+/// a checkpoint inside of a chapter
+#[derive(Hash, Eq, PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Split {
+    Chapter(Chapter),
+    /// A checkpoint, identified by the room it is in
+    Checkpoint(Chapter, String),
+}
+
+impl Split {
+    pub fn chapter(&self) -> &Chapter {
+        match self {
+            Self::Chapter(chapter) | Self::Checkpoint(chapter, _) => chapter,
+        }
+    }
+
+    pub fn display_name(&self) -> String {
+        match self {
+            Self::Chapter(chapter) => chapter.display_name(),
+            Self::Checkpoint(chapter, room) => {
+                let name = chapter.checkpoints().iter().find(|checkpoint| checkpoint.room == room).map_or(room.as_str(), |checkpoint| checkpoint.name);
+                format!("{} {}", chapter.display_name(), name)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Split {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Chapter(chapter) => write!(f, "{}", chapter),
+            Self::Checkpoint(chapter, room) => write!(f, "{}@{}", chapter, room),
+        }
+    }
+}
+
+impl TryFrom<String> for Split {
+    type Error = anyhow::Error;
+
+    /// Parses a split. Checkpoints are written as the chapter and the checkpoint separated by an
+    /// @, where the checkpoint can either be its name or its room, i.e. "7A@500 M" or "7A@b-00"
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::parse_with(&value, |chapter| Chapter::try_from(chapter.to_owned()))
+    }
+}
+
+impl Split {
+    /// Parses a split like `try_from`, but uses `Chapter::parse_lenient` for the chapter
+    pub fn parse_lenient(value: &str) -> anyhow::Result<Self> {
+        Self::parse_with(value, Chapter::parse_lenient)
+    }
+
+    fn parse_with(value: &str, parse_chapter: impl Fn(&str) -> anyhow::Result<Chapter>) -> anyhow::Result<Self> {
+        let Some((chapter, checkpoint)) = value.split_once('@') else {
+            return Ok(Self::Chapter(parse_chapter(value)?));
+        };
+        let chapter = parse_chapter(chapter.trim())?;
+        let checkpoint = checkpoint.trim();
+        // There is no checkpoint metadata for custom chapters, so their checkpoints can only be
+        // referred to by room
+        if let Chapter::Custom(..) = chapter {
+            return Ok(Self::Checkpoint(chapter, checkpoint.to_owned()));
+        }
+        let room = chapter.checkpoint(checkpoint).map(|checkpoint| checkpoint.room.to_owned()).ok_or_else(|| {
+            let names: Vec<&str> = chapter.checkpoints().iter().map(|checkpoint| checkpoint.name).collect();
+            if names.is_empty() {
+                anyhow!("{} does not have any checkpoints", chapter)
+            } else {
+                anyhow!("{} does not have a checkpoint '{}'. expected one of: {}", chapter, checkpoint, names.join(", "))
+            }
+        })?;
+        Ok(Self::Checkpoint(chapter, room))
+    }
+}
+
+impl From<Split> for String {
+    fn from(split: Split) -> Self {
+        split.to_string()
+    }
+}
+
 pub const ANY_PERCENT_ROUTE: [Chapter; 8] = [
     Chapter::Prologue,
     Chapter::City(Side::A),
@@ -149,3 +446,324 @@ pub const ANY_PERCENT_ROUTE: [Chapter; 8] = [
     Chapter::Reflection(Side::A),
     Chapter::Summit(Side::A),
 ];
+
+/// An ordered list of splits that make up a run, along with the name of the category it
+/// belongs to
+#[derive(Debug, Clone, Deserialize)]
+pub struct Route {
+    #[serde(default)]
+    pub name: String,
+    pub splits: Vec<RouteSplit>,
+}
+
+impl Route {
+    pub fn new(name: &str, splits: Vec<Chapter>) -> Self {
+        Self {
+            name: name.to_owned(),
+            splits: splits.into_iter().map(|chapter| RouteSplit { split: Split::Chapter(chapter), name: None }).collect(),
+        }
+    }
+
+    /// A route for practicing a single chapter, which splits on every checkpoint in the chapter
+    pub fn individual_level(chapter: Chapter) -> Self {
+        let mut splits: Vec<RouteSplit> = chapter.checkpoints().iter()
+            .map(|checkpoint| RouteSplit { split: Split::Checkpoint(chapter.clone(), checkpoint.room.to_owned()), name: None })
+            .collect();
+        splits.push(RouteSplit { split: Split::Chapter(chapter.clone()), name: None });
+        Self {
+            name: chapter.to_string(),
+            splits,
+        }
+    }
+
+    /// Loads a user-defined route from a JSON or TOML file. The format is picked from the file
+    /// extension. If the file does not name the route, the file name is used instead
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path).with_context(|| format!("could not read route file {}", path.display()))?;
+        let mut route: Self = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::from_str(&contents).map_err(anyhow::Error::from),
+            Some("toml") => toml::from_str(&contents).map_err(anyhow::Error::from),
+            _ => Err(anyhow!("unsupported route file format. expected a .json or .toml file")),
+        }.with_context(|| format!("could not load route file {}", path.display()))?;
+
+        if route.splits.is_empty() {
+            return Err(anyhow!("route file {} does not contain any splits", path.display()));
+        }
+        if route.name.is_empty() {
+            route.name = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+        }
+        Ok(route)
+    }
+
+    /// All of the built-in routes, one for each of the main leaderboard categories
+    pub fn builtins() -> Vec<Self> {
+        vec![
+            Self::any_percent(),
+            Self::all_sides("All A-Sides", Side::A),
+            Self::all_sides("All B-Sides", Side::B),
+            Self::all_sides("All C-Sides", Side::C),
+            Self::true_ending(),
+            Self::hundred_percent(),
+            Self::all_chapters(),
+        ]
+    }
+
+    /// Looks up a built-in route by name. Case, spaces and punctuation are ignored, so
+    /// "all a-sides" and "AllASides" both find the All A-Sides route
+    pub fn builtin(name: &str) -> Option<Self> {
+        let name = normalize_name(name);
+        Self::builtins().into_iter().find(|route| route.id() == name)
+    }
+
+    /// A name for the route that is safe to use in file paths
+    pub fn id(&self) -> String {
+        normalize_name(&self.name)
+    }
+
+    pub fn any_percent() -> Self {
+        Self::new("Any%", ANY_PERCENT_ROUTE.to_vec())
+    }
+
+    pub fn true_ending() -> Self {
+        let mut splits = ANY_PERCENT_ROUTE.to_vec();
+        splits.push(Chapter::Core(Side::A));
+        Self::new("True Ending", splits)
+    }
+
+    pub fn hundred_percent() -> Self {
+        let mut splits = vec![Chapter::Prologue];
+        for index in MAIN_CHAPTER_INDICES {
+            for side in [Side::A, Side::B, Side::C] {
+                splits.push(Chapter::from_index(index, side).unwrap());
+            }
+        }
+        Self::new("100%", splits)
+    }
+
+    pub fn all_chapters() -> Self {
+        let mut splits = ANY_PERCENT_ROUTE.to_vec();
+        splits.extend([Chapter::Epilogue, Chapter::Core(Side::A), Chapter::Farewell]);
+        Self::new("All Chapters", splits)
+    }
+
+    fn all_sides(name: &str, side: Side) -> Self {
+        let mut splits = Vec::new();
+        // The prologue has to be played before any other chapter is unlocked, so it is only
+        // part of a run on a fresh file
+        if side == Side::A {
+            splits.push(Chapter::Prologue);
+        }
+        for index in MAIN_CHAPTER_INDICES {
+            splits.push(Chapter::from_index(index, side.clone()).unwrap());
+        }
+        Self::new(name, splits)
+    }
+}
+
+/// A single split of a route. If the split has a name, it is displayed instead of the default
+/// name of the split
+#[derive(Debug, Clone)]
+pub struct RouteSplit {
+    pub split: Split,
+    pub name: Option<String>,
+}
+
+impl RouteSplit {
+    pub fn display_name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| self.split.display_name())
+    }
+}
+
+// Splits in route files are either a plain split string like "1A" or "7A@500 M", or a table with a
+// split and a display name. This is implemented by hand instead of using an untagged enum, so that the
+// error for a bad entry says what was wrong with it, rather than that no variant matched
+impl<'de> Deserialize<'de> for RouteSplit {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct NamedSplit {
+            #[serde(alias = "chapter")]
+            split: String,
+            name: Option<String>,
+        }
+
+        struct RouteSplitVisitor;
+
+        impl<'de> Visitor<'de> for RouteSplitVisitor {
+            type Value = RouteSplit;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a split or a table with a split and a name")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                let split = Split::parse_lenient(value).map_err(E::custom)?;
+                Ok(RouteSplit { split, name: None })
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                let NamedSplit { split, name } = NamedSplit::deserialize(MapAccessDeserializer::new(map))?;
+                let split = Split::parse_lenient(&split).map_err(de::Error::custom)?;
+                Ok(RouteSplit { split, name })
+            }
+        }
+
+        deserializer.deserialize_any(RouteSplitVisitor)
+    }
+}
+
+/// Indices of the chapters that have a B- and C-side, in the order they are played
+const MAIN_CHAPTER_INDICES: [u8; 8] = [1, 2, 3, 4, 5, 6, 7, 9];
+
+fn normalize_name(name: &str) -> String {
+    name.chars().filter(|c| c.is_ascii_alphanumeric()).map(|c| c.to_ascii_lowercase()).collect()
+}
+
+/// The Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_chapter_round_trips() {
+        for chapter in Chapter::all() {
+            assert_eq!(Chapter::try_from(chapter.to_string()).unwrap(), chapter);
+        }
+    }
+
+    #[test]
+    fn only_existing_sides_are_chapters() {
+        assert_eq!(Chapter::all().len(), 3 + 8 * 3);
+        assert!(Chapter::from_index(1, Side::C).is_ok());
+        assert!(Chapter::from_index(0, Side::B).is_err());
+        assert!(Chapter::from_index(10, Side::B).is_err());
+        assert!(Chapter::from_index(11, Side::A).is_err());
+    }
+
+    #[test]
+    fn custom_chapters_round_trip() {
+        let chapter = Chapter::Custom("StrawberryJam2021/1-Beginner/maya".to_owned(), Side::B);
+        assert_eq!(Chapter::try_from(chapter.to_string()).unwrap(), chapter);
+        assert_eq!(Chapter::try_from("StrawberryJam2021/1-Beginner/maya".to_owned()).unwrap(), Chapter::Custom("StrawberryJam2021/1-Beginner/maya".to_owned(), Side::A));
+        assert_eq!(Chapter::try_from("Celeste/1-ForsakenCity:B".to_owned()).unwrap(), Chapter::City(Side::B));
+    }
+
+    #[test]
+    fn try_from_rejects_missing_sides() {
+        assert!(Chapter::try_from("Prologue".to_owned()).is_ok());
+        assert!(Chapter::try_from("Prologue B".to_owned()).unwrap_err().to_string().contains("does not have a B-side"));
+        assert!(Chapter::try_from("9B".to_owned()).is_err());
+        assert!(Chapter::try_from("1D".to_owned()).is_err());
+        assert!(Chapter::try_from("City".to_owned()).is_err());
+    }
+
+    #[test]
+    fn splits_round_trip() {
+        for chapter in Chapter::all() {
+            let split = Split::Chapter(chapter.clone());
+            assert_eq!(Split::try_from(split.to_string()).unwrap(), split);
+            for checkpoint in chapter.checkpoints() {
+                let split = Split::Checkpoint(chapter.clone(), checkpoint.room.to_owned());
+                assert_eq!(Split::try_from(split.to_string()).unwrap(), split);
+                assert_eq!(Split::try_from(format!("{}@{}", chapter, checkpoint.name)).unwrap(), split);
+            }
+        }
+    }
+
+    #[test]
+    fn parse_lenient_accepts_every_chapter() {
+        for chapter in Chapter::all() {
+            assert_eq!(Chapter::parse_lenient(&chapter.to_string()).unwrap(), chapter);
+            assert_eq!(Chapter::parse_lenient(&chapter.full_name()).unwrap(), chapter);
+        }
+    }
+
+    #[test]
+    fn parse_lenient_accepts_aliases() {
+        for alias in ["1a", "1", "City A", "city", "Forsaken City", "forsaken-city a side", "1 A-Side"] {
+            assert_eq!(Chapter::parse_lenient(alias).unwrap(), Chapter::City(Side::A), "{}", alias);
+        }
+        assert_eq!(Chapter::parse_lenient("Forsaken City B-Side").unwrap(), Chapter::City(Side::B));
+        assert_eq!(Chapter::parse_lenient("core c").unwrap(), Chapter::Core(Side::C));
+        assert_eq!(Chapter::parse_lenient("summit").unwrap(), Chapter::Summit(Side::A));
+        assert_eq!(Chapter::parse_lenient("farewell").unwrap(), Chapter::Farewell);
+    }
+
+    #[test]
+    fn parse_lenient_suggests_close_chapters() {
+        let error = Chapter::parse_lenient("celestial resrt").unwrap_err().to_string();
+        assert!(error.contains("did you mean 3A"), "{}", error);
+        assert!(Chapter::parse_lenient("farewell b").unwrap_err().to_string().contains("does not have a B-side"));
+        assert_eq!(Chapter::parse_lenient("xyzzy").unwrap_err().to_string(), "unknown chapter 'xyzzy'");
+    }
+
+    #[test]
+    fn parse_lenient_parses_checkpoints() {
+        assert_eq!(Split::parse_lenient("summit a @ 500 m").unwrap(), Split::Checkpoint(Chapter::Summit(Side::A), "b-00".to_owned()));
+        assert!(Split::parse_lenient("7A@600 M").is_err());
+        assert!(Split::parse_lenient("Prologue@anything").unwrap_err().to_string().contains("does not have any checkpoints"));
+    }
+
+    /// Writes a route file to a temporary directory and loads it
+    fn load_route(file_name: &str, contents: &str) -> anyhow::Result<Route> {
+        let dir = std::env::temp_dir().join(format!("rcas-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(file_name);
+        std::fs::write(&path, contents).unwrap();
+        let route = Route::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        route
+    }
+
+    #[test]
+    fn loads_json_and_toml_routes() {
+        let route = load_route("json-route.json", r#"{"splits": ["1a", {"split": "7A@500 M", "name": "500"}]}"#).unwrap();
+        assert_eq!(route.name, "json-route");
+        assert_eq!(route.splits[0].split, Split::Chapter(Chapter::City(Side::A)));
+        assert_eq!(route.splits[1].split, Split::Checkpoint(Chapter::Summit(Side::A), "b-00".to_owned()));
+        assert_eq!(route.splits[1].display_name(), "500");
+
+        let route = load_route("toml-route.toml", "name = \"Mine\"\nsplits = [\"Forsaken City\", { chapter = \"2B\" }]\n").unwrap();
+        assert_eq!(route.name, "Mine");
+        assert_eq!(route.splits[1].split, Split::Chapter(Chapter::Site(Side::B)));
+    }
+
+    #[test]
+    fn json_errors_give_the_position_of_a_bad_entry() {
+        let error = load_route("bad.json", "{\n  \"splits\": [\n    \"1A\",\n    \"Prologue B\"\n  ]\n}\n").unwrap_err();
+        let message = format!("{:#}", error);
+        assert!(message.contains("Prologue does not have a B-side"), "{}", message);
+        assert!(message.contains("line 4 column 16"), "{}", message);
+    }
+
+    #[test]
+    fn toml_errors_give_the_position_of_a_bad_entry() {
+        let error = load_route("bad.toml", "splits = [\n  \"1A\",\n  \"Prologue B\",\n]\n").unwrap_err();
+        let message = format!("{:#}", error);
+        assert!(message.contains("Prologue does not have a B-side"), "{}", message);
+        assert!(message.contains("line 3, column 3"), "{}", message);
+    }
+
+    #[test]
+    fn rejects_unknown_fields_and_empty_routes() {
+        let message = format!("{:#}", load_route("unknown.json", r#"{"splits": [{"split": "1A", "time": 1}]}"#).unwrap_err());
+        assert!(message.contains("unknown field `time`"), "{}", message);
+        let message = format!("{:#}", load_route("empty.toml", "splits = []\n").unwrap_err());
+        assert!(message.contains("does not contain any splits"), "{}", message);
+        assert!(load_route("route.yaml", "splits: [1A]\n").is_err());
+    }
+}
@@ -1,5 +1,6 @@
 use anyhow::Result;
 
+use config::Config;
 use timer::Timer;
 
 mod timer;
@@ -8,9 +9,18 @@ mod saves;
 mod watch;
 mod terminal;
 mod table;
+mod config;
+mod record;
+mod bindings;
+mod clock;
+mod run;
+mod history;
+mod jsonl;
+mod comparison;
 
 fn main() -> Result<()> {
-    let timer = Timer::new()?;
+    let config = Config::from_args()?;
+    let timer = Timer::new(config)?;
     timer.run()?;
     Ok(())
 }
@@ -0,0 +1,82 @@
+use std::{fs::File, path::Path, time::{Duration, Instant}};
+
+use anyhow::{anyhow, Context, Result};
+use futures::{channel::mpsc::Sender, SinkExt};
+use serde::{Serialize, Deserialize};
+
+use crate::{jsonl, saves::SaveStats, watch::WatchEvent};
+
+/// A save update in a recording, along with how long after the start of the recording it happened
+#[derive(Serialize, Deserialize)]
+struct RecordedUpdate {
+    time: Duration,
+    save: SaveStats,
+    /// Whether this is the save that was there when the recording started, rather than an update
+    #[serde(default)]
+    initial: bool,
+}
+
+/// Writes every save update to a file, one JSON object per line, so that it can be replayed later
+pub struct Recorder {
+    file: File,
+    start: Instant,
+}
+
+impl Recorder {
+    pub fn new(path: &Path) -> Result<Self> {
+        let file = File::create(path).with_context(|| format!("could not create recording {}", path.display()))?;
+        Ok(Self { file, start: Instant::now() })
+    }
+
+    pub fn record(&mut self, save: &SaveStats) -> Result<()> {
+        self.write(save, false)
+    }
+
+    /// Records the save that was there when rcas started, which replays start from
+    pub fn record_initial(&mut self, save: &SaveStats) -> Result<()> {
+        self.write(save, true)
+    }
+
+    fn write(&mut self, save: &SaveStats, initial: bool) -> Result<()> {
+        let update = RecordedUpdate { time: self.start.elapsed(), save: save.clone(), initial };
+        // Every update is written right away, so that the recording is still usable if rcas crashes
+        jsonl::write_line(&mut self.file, &update)
+    }
+}
+
+/// Reads a recording made by a [`Recorder`]
+fn load_recording(path: &Path) -> Result<Vec<RecordedUpdate>> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("could not open recording {}", path.display()))?;
+    jsonl::parse(&text, path, "update")
+}
+
+/// Sends the updates of a recording with the same time between them as when they were recorded,
+/// divided by the speed. Once the recording is over, this waits until the receiver is dropped, since
+/// that would otherwise stop the timer. Returns the save that was there when the recording started,
+/// if it was recorded, which is not sent as an update
+pub fn replay(path: &Path, speed: f64, mut tx: Sender<WatchEvent>) -> Result<Option<SaveStats>> {
+    if speed <= 0.0 {
+        return Err(anyhow!("the replay speed has to be more than 0"));
+    }
+    let mut updates = load_recording(path)?;
+    let initial = match updates.first() {
+        Some(update) if update.initial => Some(updates.remove(0).save),
+        _ => None,
+    };
+    let start = Instant::now();
+
+    std::thread::spawn(move || {
+        for update in updates {
+            let due = update.time.div_f64(speed);
+            std::thread::sleep(due.saturating_sub(start.elapsed()));
+            if futures::executor::block_on(tx.send(WatchEvent::SaveUpdated(update.save))).is_err() {
+                return;
+            }
+        }
+        let _ = futures::executor::block_on(tx.send(WatchEvent::ReplayFinished));
+        while !tx.is_closed() {
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    });
+    Ok(initial)
+}
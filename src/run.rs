@@ -0,0 +1,323 @@
+use std::{collections::HashSet, time::SystemTime};
+
+use anyhow::{anyhow, Result};
+
+use crate::levels::{Route, Split};
+
+/// Where the current attempt is in its lifecycle
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunState {
+    /// No attempt has been seen yet
+    NoAttempt,
+    Running,
+    /// Every split of the route has been reached or skipped
+    Finished,
+    /// The attempt was abandoned, and the next one has not started yet
+    Reset,
+}
+
+impl RunState {
+    pub fn description(&self) -> &str {
+        match self {
+            Self::NoAttempt => "there is no attempt",
+            Self::Running => "the attempt is running",
+            Self::Finished => "the attempt is finished",
+            Self::Reset => "the attempt was reset",
+        }
+    }
+}
+
+/// Something that happens to an attempt
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Transition {
+    Start,
+    Split(Split),
+    Undo(Split),
+    Skip(Split),
+    Finish,
+    Reset,
+}
+
+impl Transition {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Start => "start",
+            Self::Split(_) => "split",
+            Self::Undo(_) => "undo",
+            Self::Skip(_) => "skip",
+            Self::Finish => "finish",
+            Self::Reset => "reset",
+        }
+    }
+}
+
+/// The lifecycle of the current attempt on a route, along with which splits have been done in it
+pub struct Run {
+    state: RunState,
+    route: Vec<Split>,
+    /// The splits that have been reached or skipped, in the order it happened
+    done: Vec<Split>,
+    skipped: HashSet<Split>,
+    /// The splits that were already reached when rcas started, by an attempt it did not see
+    done_before: HashSet<Split>,
+    /// When the attempt was first seen running
+    started: Option<SystemTime>,
+}
+
+impl Run {
+    pub fn new(route: &Route) -> Self {
+        Self {
+            state: RunState::NoAttempt,
+            route: route.splits.iter().map(|route_split| route_split.split.clone()).collect(),
+            done: Vec::new(),
+            skipped: HashSet::new(),
+            done_before: HashSet::new(),
+            started: None,
+        }
+    }
+
+    pub fn state(&self) -> RunState {
+        self.state
+    }
+
+    pub fn started(&self) -> Option<SystemTime> {
+        self.started
+    }
+
+    /// Whether a split has been reached or skipped in this attempt, or was reached before it
+    pub fn is_done(&self, split: &Split) -> bool {
+        self.done.contains(split) || self.done_before.contains(split)
+    }
+
+    pub fn is_skipped(&self, split: &Split) -> bool {
+        self.skipped.contains(split)
+    }
+
+    pub fn has_skips(&self) -> bool {
+        !self.skipped.is_empty()
+    }
+
+    /// Whether any split was reached, rather than skipped, in this attempt
+    pub fn has_splits(&self) -> bool {
+        self.done.iter().any(|split| !self.skipped.contains(split))
+    }
+
+    /// Whether some splits were reached before rcas started, so the attempt is not complete
+    pub fn has_done_before(&self) -> bool {
+        !self.done_before.is_empty()
+    }
+
+    /// Whether every split of the route has been reached or skipped
+    pub fn all_done(&self) -> bool {
+        self.route.iter().all(|split| self.is_done(split))
+    }
+
+    /// Whether the attempt can finish, which needs at least one split to be done in it
+    pub fn can_finish(&self) -> bool {
+        self.state == RunState::Running && self.all_done() && !self.done.is_empty()
+    }
+
+    /// The first split of the route that has not been reached or skipped
+    pub fn next_split(&self) -> Option<&Split> {
+        self.route.iter().find(|split| !self.is_done(split))
+    }
+
+    /// Marks a split that the save already shows as reached before any attempt was seen. It is
+    /// not split on, and does nothing else
+    pub fn mark_done_before(&mut self, split: &Split) {
+        if self.state == RunState::NoAttempt && self.route.contains(split) {
+            self.done_before.insert(split.clone());
+        }
+    }
+
+    /// The split that was reached or skipped most recently
+    pub fn last_done(&self) -> Option<&Split> {
+        self.done.last()
+    }
+
+    /// Moves the attempt along. Returns an error if the transition can't happen in the current
+    /// state, in which case nothing changes
+    pub fn apply(&mut self, transition: &Transition) -> Result<()> {
+        match (self.state, transition) {
+            // Splits that were done before the first attempt stay done, since the save still shows
+            // them as reached
+            (RunState::NoAttempt | RunState::Reset | RunState::Finished, Transition::Start) => {
+                self.done.clear();
+                self.skipped.clear();
+                self.started = Some(SystemTime::now());
+                self.state = RunState::Running;
+            },
+            (RunState::Running, Transition::Split(split)) if self.route.contains(split) && !self.is_done(split) => {
+                self.done.push(split.clone());
+            },
+            (RunState::Running, Transition::Skip(split)) if self.route.contains(split) && !self.is_done(split) => {
+                self.done.push(split.clone());
+                self.skipped.insert(split.clone());
+            },
+            (RunState::Running | RunState::Finished, Transition::Undo(split)) if self.done.last() == Some(split) => {
+                self.done.pop();
+                self.skipped.remove(split);
+                self.state = RunState::Running;
+            },
+            (RunState::Running, Transition::Finish) if self.can_finish() => self.state = RunState::Finished,
+            (_, Transition::Reset) => {
+                self.done.clear();
+                self.skipped.clear();
+                self.done_before.clear();
+                self.started = None;
+                self.state = RunState::Reset;
+            },
+            (state, transition) => return Err(anyhow!("can't {} while {}", transition.name(), state.description())),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::levels::{Chapter, Side};
+
+    fn split(name: &str) -> Split {
+        Split::try_from(name.to_owned()).unwrap()
+    }
+
+    /// A run on 1A with its two checkpoints, which has been started
+    fn running() -> Run {
+        let mut run = Run::new(&Route::individual_level(Chapter::City(Side::A)));
+        run.apply(&Transition::Start).unwrap();
+        run
+    }
+
+    #[test]
+    fn splits_in_order_and_finishes() {
+        let mut run = running();
+        assert_eq!(run.state(), RunState::Running);
+        assert!(run.started().is_some());
+        for name in ["1A@6", "1A@9b", "1A"] {
+            assert!(!run.can_finish());
+            assert_eq!(run.next_split(), Some(&split(name)));
+            run.apply(&Transition::Split(split(name))).unwrap();
+        }
+        assert!(run.all_done());
+        run.apply(&Transition::Finish).unwrap();
+        assert_eq!(run.state(), RunState::Finished);
+    }
+
+    #[test]
+    fn split_needs_a_started_attempt() {
+        let mut run = Run::new(&Route::individual_level(Chapter::City(Side::A)));
+        assert!(run.apply(&Transition::Split(split("1A@6"))).is_err());
+        assert_eq!(run.state(), RunState::NoAttempt);
+        assert!(!run.is_done(&split("1A@6")));
+
+        run.apply(&Transition::Reset).unwrap();
+        assert!(run.apply(&Transition::Split(split("1A@6"))).is_err());
+        assert_eq!(run.state(), RunState::Reset);
+    }
+
+    #[test]
+    fn rejects_splits_that_are_done_or_not_in_the_route() {
+        let mut run = running();
+        run.apply(&Transition::Split(split("1A@6"))).unwrap();
+        assert!(run.apply(&Transition::Split(split("1A@6"))).is_err());
+        assert!(run.apply(&Transition::Split(split("2A"))).is_err());
+        assert!(run.apply(&Transition::Skip(split("1A@6"))).is_err());
+        assert!(run.apply(&Transition::Finish).is_err());
+    }
+
+    #[test]
+    fn splits_done_before_the_attempt_stay_done() {
+        let mut run = Run::new(&Route::individual_level(Chapter::City(Side::A)));
+        run.mark_done_before(&split("1A@6"));
+        assert!(run.is_done(&split("1A@6")));
+        assert!(run.has_done_before());
+        assert_eq!(run.last_done(), None);
+
+        run.apply(&Transition::Start).unwrap();
+        assert!(run.is_done(&split("1A@6")));
+        assert_eq!(run.next_split(), Some(&split("1A@9b")));
+        run.apply(&Transition::Split(split("1A@9b"))).unwrap();
+        run.apply(&Transition::Split(split("1A"))).unwrap();
+        assert!(run.can_finish());
+
+        run.apply(&Transition::Reset).unwrap();
+        assert!(!run.has_done_before());
+        assert!(!run.is_done(&split("1A@6")));
+    }
+
+    #[test]
+    fn nothing_is_marked_done_before_once_an_attempt_started() {
+        let mut run = running();
+        run.mark_done_before(&split("1A@6"));
+        assert!(!run.is_done(&split("1A@6")));
+    }
+
+    #[test]
+    fn can_not_finish_without_a_split_in_the_attempt() {
+        let mut run = Run::new(&Route::individual_level(Chapter::City(Side::A)));
+        for name in ["1A@6", "1A@9b", "1A"] {
+            run.mark_done_before(&split(name));
+        }
+        run.apply(&Transition::Start).unwrap();
+        assert!(run.all_done());
+        assert!(!run.can_finish());
+        assert!(run.apply(&Transition::Finish).is_err());
+    }
+
+    #[test]
+    fn skips_are_remembered() {
+        let mut run = running();
+        run.apply(&Transition::Skip(split("1A@6"))).unwrap();
+        assert!(run.is_skipped(&split("1A@6")));
+        assert!(run.has_skips());
+        assert!(!run.has_splits());
+        run.apply(&Transition::Undo(split("1A@6"))).unwrap();
+        assert!(!run.has_skips());
+        assert!(!run.is_done(&split("1A@6")));
+    }
+
+    #[test]
+    fn undo_takes_back_only_the_last_split() {
+        let mut run = running();
+        run.apply(&Transition::Split(split("1A@6"))).unwrap();
+        run.apply(&Transition::Split(split("1A@9b"))).unwrap();
+        assert!(run.apply(&Transition::Undo(split("1A@6"))).is_err());
+        run.apply(&Transition::Undo(split("1A@9b"))).unwrap();
+        assert_eq!(run.last_done(), Some(&split("1A@6")));
+    }
+
+    #[test]
+    fn undo_from_finished_runs_again() {
+        let mut run = running();
+        for name in ["1A@6", "1A@9b", "1A"] {
+            run.apply(&Transition::Split(split(name))).unwrap();
+        }
+        run.apply(&Transition::Finish).unwrap();
+        assert!(run.apply(&Transition::Split(split("1A"))).is_err());
+
+        run.apply(&Transition::Undo(split("1A"))).unwrap();
+        assert_eq!(run.state(), RunState::Running);
+        assert_eq!(run.next_split(), Some(&split("1A")));
+        run.apply(&Transition::Split(split("1A"))).unwrap();
+        run.apply(&Transition::Finish).unwrap();
+        assert_eq!(run.state(), RunState::Finished);
+    }
+
+    #[test]
+    fn reset_forgets_the_attempt() {
+        let mut run = running();
+        run.apply(&Transition::Skip(split("1A@6"))).unwrap();
+        run.apply(&Transition::Reset).unwrap();
+        assert_eq!(run.state(), RunState::Reset);
+        assert_eq!(run.started(), None);
+        assert_eq!(run.last_done(), None);
+        assert!(!run.has_skips());
+        assert!(run.apply(&Transition::Undo(split("1A@6"))).is_err());
+        assert!(run.apply(&Transition::Finish).is_err());
+
+        run.apply(&Transition::Start).unwrap();
+        assert_eq!(run.state(), RunState::Running);
+        assert_eq!(run.next_split(), Some(&split("1A@6")));
+    }
+}
@@ -1,66 +1,500 @@
-use std::{fs::File, io::BufReader, path::Path};
-use std::collections::HashMap;
+use std::{fs::File, io::{BufRead, BufReader}, path::{Path, PathBuf}, time::SystemTime};
+use std::collections::{HashMap, HashSet};
 use std::time::Duration;
-use anyhow::anyhow;
+use anyhow::{anyhow, Context};
 use quick_xml::events::BytesStart;
 use quick_xml::{events::Event, reader::Reader, name::QName};
+use serde::{Serialize, Deserialize};
 
-use crate::levels::{Chapter, Side};
+use crate::levels::{Chapter, Side, Split};
 
-pub type TimeMap = HashMap<Chapter, Duration>;
+pub type TimeMap = HashMap<Split, Duration>;
 
-pub fn load_save(path: &Path) -> anyhow::Result<TimeMap> {
-    let mut data = HashMap::new();
+/// Everything the save file records about a single side of a chapter
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AreaModeStats {
+    pub time_played: Duration,
+    pub best_time: Duration,
+    pub best_full_clear_time: Duration,
+    pub deaths: u32,
+    pub best_deaths: u32,
+    pub best_dashes: u32,
+    pub total_strawberries: u32,
+    pub heart_gem: bool,
+    pub completed: bool,
+    pub single_run_completed: bool,
+    pub full_clear: bool,
+    /// The rooms of the checkpoints that have been reached, in the order they were reached
+    pub checkpoints: Vec<String>,
+}
+
+impl AreaModeStats {
+    fn from_tag(tag: &BytesStart) -> anyhow::Result<Self> {
+        Ok(Self {
+            time_played: parse_ticks(&find_attr(b"TimePlayed", tag)?)?,
+            best_time: parse_ticks(&find_attr(b"BestTime", tag)?)?,
+            best_full_clear_time: parse_ticks(&find_attr(b"BestFullClearTime", tag)?)?,
+            deaths: find_attr(b"Deaths", tag)?.parse()?,
+            best_deaths: find_attr(b"BestDeaths", tag)?.parse()?,
+            best_dashes: find_attr(b"BestDashes", tag)?.parse()?,
+            total_strawberries: find_attr(b"TotalStrawberries", tag)?.parse()?,
+            heart_gem: find_attr(b"HeartGem", tag)?.parse()?,
+            completed: find_attr(b"Completed", tag)?.parse()?,
+            single_run_completed: find_attr(b"SingleRunCompleted", tag)?.parse()?,
+            full_clear: find_attr(b"FullClear", tag)?.parse()?,
+            checkpoints: Vec::new(),
+        })
+    }
+}
+
+/// Which of the times the save file stores for each chapter is used as the time of the chapter
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimingSource {
+    /// The total time spent in the chapter, across every attempt. This is the time of a full game
+    /// run on a fresh file
+    #[default]
+    TimePlayed,
+    /// The fastest completion of the chapter, for individual level runs
+    BestTime,
+    /// The fastest full clear of the chapter
+    BestFullClearTime,
+    /// The file timer at the point each split was reached. Unlike the time played, this includes
+    /// time spent in menus and replaying chapters, which is how full game runs are officially
+    /// timed. The file timer is not stored per chapter, so splits that were reached before rcas
+    /// was watching fall back to the time played of their chapter
+    FileTime,
+}
+
+impl TimingSource {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::TimePlayed => "Time Played",
+            Self::BestTime => "Best Time",
+            Self::BestFullClearTime => "Best Full Clear",
+            Self::FileTime => "File Time",
+        }
+    }
+
+    /// A name for the timing source that is safe to use in file paths
+    pub fn id(&self) -> &str {
+        match self {
+            Self::TimePlayed => "timeplayed",
+            Self::BestTime => "besttime",
+            Self::BestFullClearTime => "bestfullclear",
+            Self::FileTime => "filetime",
+        }
+    }
+
+    /// The time of a chapter according to this source. Zero if the chapter has not been completed
+    /// in the way the source requires
+    pub fn time(&self, stats: &AreaModeStats) -> Duration {
+        match self {
+            Self::TimePlayed | Self::FileTime if stats.best_time.is_zero() => Duration::ZERO,
+            Self::TimePlayed | Self::FileTime => stats.time_played,
+            Self::BestTime => stats.best_time,
+            Self::BestFullClearTime => stats.best_full_clear_time,
+        }
+    }
+
+    /// The timing source after this one, going back to the first one after the last
+    pub fn next(&self) -> Self {
+        match self {
+            Self::TimePlayed => Self::BestTime,
+            Self::BestTime => Self::BestFullClearTime,
+            Self::BestFullClearTime => Self::FileTime,
+            Self::FileTime => Self::TimePlayed,
+        }
+    }
+}
+
+impl std::fmt::Display for TimingSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl TryFrom<String> for TimingSource {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let name: String = value.chars().filter(|c| c.is_ascii_alphanumeric()).map(|c| c.to_ascii_lowercase()).collect();
+        match name.as_str() {
+            "timeplayed" | "played" => Ok(Self::TimePlayed),
+            "besttime" | "best" => Ok(Self::BestTime),
+            "bestfullcleartime" | "bestfullclear" | "fullclear" | "fc" => Ok(Self::BestFullClearTime),
+            "filetime" | "file" => Ok(Self::FileTime),
+            _ => Err(anyhow!("invalid timing source: '{}'. expected one of: played, best, fullclear, file", value)),
+        }
+    }
+}
+
+/// Totals over every chapter in a save file
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SaveTotals {
+    pub time_played: Duration,
+    pub deaths: u32,
+    pub strawberries: u32,
+    pub hearts: u32,
+    pub completed: u32,
+}
+
+/// The chapter the player is currently in. The game saves this so that the chapter can be
+/// resumed after closing the game
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    pub chapter: Chapter,
+    /// The room the player is in
+    pub room: String,
+    /// The point in the room the player respawns at
+    pub respawn_point: Option<(i32, i32)>,
+    /// The time spent in the chapter since it was started
+    pub time: Duration,
+    pub deaths: u32,
+}
+
+/// The stats of every chapter side that has been played in a save file
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SaveStats {
+    pub areas: HashMap<Chapter, AreaModeStats>,
+    /// The file timer, which is the total time spent on the file
+    pub file_time: Duration,
+    pub current_session: Option<Session>,
+}
+
+impl SaveStats {
+    /// The time of each completed chapter, according to the given timing source
+    pub fn time_map(&self, source: TimingSource) -> TimeMap {
+        self.areas.iter()
+            .map(|(chapter, stats)| (Split::Chapter(chapter.clone()), source.time(stats)))
+            .filter(|(_, time)| !time.is_zero())
+            .collect()
+    }
+
+    /// Every checkpoint that has been reached, along with the time spent in its chapter at the
+    /// point the save was written
+    pub fn checkpoint_map(&self) -> TimeMap {
+        self.areas.iter()
+            .flat_map(|(chapter, stats)| stats.checkpoints.iter().map(|room| (Split::Checkpoint(chapter.clone(), room.clone()), stats.time_played)))
+            .collect()
+    }
+
+    /// Whether this save was started over since the previous one, which means a new attempt has
+    /// started. The file timer only ever goes up, unless the file was deleted and started again
+    pub fn is_reset_of(&self, previous: &SaveStats) -> bool {
+        self.file_time < previous.file_time
+    }
+
+    pub fn totals(&self) -> SaveTotals {
+        let mut totals = SaveTotals::default();
+        for stats in self.areas.values() {
+            totals.time_played += stats.time_played;
+            totals.deaths += stats.deaths;
+            totals.strawberries += stats.total_strawberries;
+            totals.hearts += u32::from(stats.heart_gem);
+            totals.completed += u32::from(stats.completed);
+        }
+        totals
+    }
+}
+
+/// Where Steam keeps its data, relative to the home directory. The Flatpak version of Steam keeps
+/// its own home directory in ~/.var/app
+const STEAM_ROOTS: [&str; 5] = [
+    ".steam/steam",
+    ".local/share/Steam",
+    ".var/app/com.valvesoftware.Steam/.steam/steam",
+    ".var/app/com.valvesoftware.Steam/.local/share/Steam",
+    ".var/app/com.valvesoftware.Steam/data/Steam",
+];
+
+/// Where the Windows version of Celeste can be installed, relative to a Wine prefix. The Windows
+/// version keeps its saves next to the game
+const WINE_SAVE_DIRS: [&str; 3] = [
+    "drive_c/Program Files (x86)/Steam/steamapps/common/Celeste/Saves",
+    "drive_c/Program Files (x86)/Celeste/Saves",
+    "drive_c/Program Files/Celeste/Saves",
+];
+
+/// Finds the directory Celeste keeps its saves in. If a directory is given, only that one is
+/// used. Otherwise, the native, Flatpak and Proton/Wine locations are tried in that order, and the
+/// first one that exists is used
+pub fn find_save_dir(save_dir: Option<&Path>) -> anyhow::Result<PathBuf> {
+    if let Some(save_dir) = save_dir {
+        let save_dir = PathBuf::from(shellexpand::tilde(&save_dir.to_string_lossy()).as_ref());
+        if !save_dir.is_dir() {
+            return Err(anyhow!("save directory {} does not exist", save_dir.display()));
+        }
+        return Ok(save_dir);
+    }
+
+    let candidates = save_dir_candidates();
+    candidates.iter().find(|candidate| candidate.is_dir()).cloned().ok_or_else(|| {
+        let tried: Vec<String> = candidates.iter().map(|candidate| format!("  {}", candidate.display())).collect();
+        anyhow!("could not find the celeste save directory. set it with --save-dir. tried:\n{}", tried.join("\n"))
+    })
+}
+
+/// Every directory that Celeste might keep its saves in, in the order they should be tried
+fn save_dir_candidates() -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    if let Some(data_home) = std::env::var_os("XDG_DATA_HOME").filter(|data_home| !data_home.is_empty()) {
+        candidates.push(PathBuf::from(data_home).join("Celeste/Saves"));
+    }
+    let Some(home) = std::env::var_os("HOME").map(PathBuf::from) else {
+        return candidates;
+    };
+    candidates.push(home.join(".local/share/Celeste/Saves"));
+    // Flatpak apps get their own XDG_DATA_HOME
+    candidates.push(home.join(".var/app/com.valvesoftware.Steam/data/Celeste/Saves"));
+    candidates.push(home.join(".var/app/com.valvesoftware.Steam/.local/share/Celeste/Saves"));
+
+    for steam_root in STEAM_ROOTS.iter().map(|root| home.join(root)) {
+        let steamapps = steam_root.join("steamapps");
+        // The Windows version of Celeste run through Proton
+        candidates.push(steamapps.join("common/Celeste/Saves"));
+        // Non-Steam versions of Celeste added to Steam get their own prefix
+        let mut prefixes: Vec<PathBuf> = std::fs::read_dir(steamapps.join("compatdata"))
+            .map(|entries| entries.filter_map(|entry| entry.ok()).map(|entry| entry.path().join("pfx")).collect())
+            .unwrap_or_default();
+        prefixes.sort();
+        for prefix in prefixes {
+            candidates.extend(WINE_SAVE_DIRS.iter().map(|dir| prefix.join(dir)));
+        }
+    }
+    candidates.extend(WINE_SAVE_DIRS.iter().map(|dir| home.join(".wine").join(dir)));
+
+    // ~/.steam/steam is usually a link to one of the other Steam roots
+    let mut seen = HashSet::new();
+    candidates.retain(|candidate| seen.insert(candidate.canonicalize().unwrap_or_else(|_| candidate.clone())));
+    candidates
+}
+
+/// The path of the save file of a slot. Slots are numbered like the files, starting from 0, so
+/// they are one less than the number shown in the game
+pub fn slot_path(save_dir: &Path, slot: u32) -> PathBuf {
+    save_dir.join(format!("{}.celeste", slot))
+}
+
+/// The slots that have a save file in the save directory, in ascending order
+pub fn save_slots(save_dir: &Path) -> anyhow::Result<Vec<u32>> {
+    Ok(slot_files(save_dir)?.into_iter().map(|(slot, _)| slot).collect())
+}
+
+/// The slot of the save file that was modified most recently, which is usually the one that is
+/// being played
+pub fn latest_slot(save_dir: &Path) -> anyhow::Result<u32> {
+    slot_files(save_dir)?
+        .into_iter()
+        .max_by_key(|(_, modified)| *modified)
+        .map(|(slot, _)| slot)
+        .ok_or(anyhow!("no save files found in {}", save_dir.display()))
+}
+
+/// Every save file in the save directory, along with when it was last modified. Other files in
+/// the directory, like settings.celeste, are skipped
+fn slot_files(save_dir: &Path) -> anyhow::Result<Vec<(u32, SystemTime)>> {
+    let mut slots = Vec::new();
+    for entry in std::fs::read_dir(save_dir).with_context(|| format!("could not read save directory {}", save_dir.display()))? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|extension| extension != "celeste") {
+            continue;
+        }
+        let Some(slot) = path.file_stem().and_then(|stem| stem.to_str()).and_then(|stem| stem.parse().ok()) else {
+            continue;
+        };
+        slots.push((slot, path.metadata()?.modified()?));
+    }
+    slots.sort_by_key(|(slot, _)| *slot);
+    Ok(slots)
+}
+
+pub fn load_save(path: &Path) -> anyhow::Result<SaveStats> {
     let file = File::open(path)?;
-    let buf_reader = BufReader::new(file);
-    let mut reader = Reader::from_reader(buf_reader);
+    parse_save(BufReader::new(file))
+}
+
+fn parse_save<R: BufRead>(save: R) -> anyhow::Result<SaveStats> {
+    let mut areas = HashMap::new();
+    let mut file_time = Duration::ZERO;
+    let mut current_session = None;
+    let mut reader = Reader::from_reader(save);
     reader.trim_text(true);
 
     let mut buf = Vec::new();
     let mut side_index = 0;
-    let mut chapter_index: Option<u8> = None;
+    let mut chapter_index: Option<String> = None;
+    // Everest stores the SID of every area. Saves from the game without Everest only have the
+    // index
+    let mut chapter_sid: Option<String> = None;
     let mut in_areas = false;
+    // Everest keeps the stats of level sets that are no longer installed in a recycle bin. These
+    // are skipped, since their chapters can't be played
+    let mut in_recycle_bin = false;
+    // The AreaModeStats tag that is currently being read
+    let mut current_mode: Option<(Chapter, AreaModeStats)> = None;
+    let mut in_checkpoints = false;
+    // How deeply nested the current tag is. The root SaveData tag has a depth of 1
+    let mut depth = 0;
+    let mut in_file_time = false;
 
     loop {
         let event = reader.read_event_into(&mut buf)?;
         match event {
+            // The save is read while it's still being written if the end is cut off
+            Event::Eof if depth > 0 => return Err(anyhow!("the save ends before the SaveData tag is closed")),
             Event::Eof => break,
             Event::Start(tag) => {
+                depth += 1;
                 match tag.name().as_ref() {
-                    b"Areas" => in_areas = true,
+                    // Other tags, like the current session, can also contain a time
+                    b"Time" if depth == 2 => in_file_time = true,
+                    // Everest saves the session under a different name when the player is in a
+                    // modded chapter, so that the save can still be loaded without Everest
+                    b"CurrentSession" | b"CurrentSession_Safe" if depth == 2 => {
+                        current_session = read_session(&mut reader, &tag)?.or(current_session);
+                        // The end tag of the session has been read as well
+                        depth -= 1;
+                    },
+                    b"LevelSetRecycleBin" => in_recycle_bin = true,
+                    b"Areas" if !in_recycle_bin => in_areas = true,
                     b"AreaStats" if in_areas => {
                         side_index = 0;
-                        let chapter = find_attr(b"ID", &tag)?;
-                        chapter_index = Some(chapter.parse::<u8>()?);
+                        chapter_index = Some(find_attr(b"ID", &tag)?);
+                        chapter_sid = find_attr(b"SID", &tag).ok();
                     }
                     b"AreaModeStats" if in_areas => {
-                        let chapter_index = chapter_index.ok_or(anyhow!("Reached an AreaModeStats tag without a chapter index being set"))?;
-                        let chapter = Chapter::from_index(chapter_index, Side::from_index(side_index)?)?;
+                        let chapter_index = chapter_index.as_ref().ok_or(anyhow!("Reached an AreaModeStats tag without a chapter index being set"))?;
+                        let side = Side::from_index(side_index)?;
+                        let chapter = match chapter_sid.as_ref() {
+                            Some(sid) => Chapter::from_sid(sid, side),
+                            None => vanilla_chapter(chapter_index, side),
+                        };
                         side_index += 1;
 
-                        let time_played = find_attr(b"TimePlayed", &tag)?.parse::<u64>()?;
-                        if time_played == 0 {
-                            continue;
-                        }
-                        let best_time = find_attr(b"BestTime", &tag)?.parse::<u64>()?;
-
-                        if best_time != 0 {
-                            data.insert(chapter,  Duration::from_micros(time_played/10));
-                        }
+                        let stats = AreaModeStats::from_tag(&tag)?;
+                        current_mode = match chapter {
+                            Ok(chapter) => Some((chapter, stats)),
+                            // Every area has stats for all three sides, even the chapters that
+                            // only have an A-side. Those sides can never be played
+                            Err(_) if stats.time_played.is_zero() => None,
+                            Err(e) => return Err(e),
+                        };
                     }
+                    b"Checkpoints" if current_mode.is_some() => in_checkpoints = true,
                     _ => (),
                 }
             },
+            Event::Text(text) if in_checkpoints => {
+                if let Some((_, stats)) = current_mode.as_mut() {
+                    stats.checkpoints.push(text.unescape()?.into_owned());
+                }
+            },
+            Event::Text(text) if in_file_time => file_time = parse_ticks(&text.unescape()?)?,
             Event::End(tag) => {
-                if tag.name().as_ref() == b"Areas" {
-                    in_areas = false;
+                depth -= 1;
+                match tag.name().as_ref() {
+                    b"Time" => in_file_time = false,
+                    b"LevelSetRecycleBin" => in_recycle_bin = false,
+                    b"Areas" => in_areas = false,
+                    b"AreaModeStats" => {
+                        if let Some((chapter, stats)) = current_mode.take() {
+                            if !stats.time_played.is_zero() {
+                                areas.insert(chapter, stats);
+                            }
+                        }
+                    },
+                    b"Checkpoints" => in_checkpoints = false,
+                    _ => (),
                 }
-            }
+            },
+            _ => (),
+        }
+        buf.clear();
+    }
+    Ok(SaveStats { areas, file_time, current_session })
+}
+
+/// Reads a session from the save, up to and including the end tag of the session. Returns none if
+/// the session does not say which chapter it is in.
+///
+/// Depending on the field, the game saves the values of the session either as attributes or as
+/// child tags. To not depend on which one is used, everything is flattened into a map from the
+/// path of the value to the value, i.e. "Level", "Area.ID" or "RespawnPoint.X"
+fn read_session<R: BufRead>(reader: &mut Reader<R>, session_tag: &BytesStart) -> anyhow::Result<Option<Session>> {
+    let mut values: HashMap<String, String> = HashMap::new();
+    insert_attrs(&mut values, "", session_tag)?;
+
+    let mut buf = Vec::new();
+    // The names of the tags inside of the session that are currently open
+    let mut path: Vec<String> = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Eof => return Err(anyhow!("reached the end of the save while reading the current session")),
+            Event::Start(tag) => {
+                path.push(std::str::from_utf8(tag.name().as_ref())?.to_owned());
+                insert_attrs(&mut values, &(path.join(".") + "."), &tag)?;
+            },
+            Event::Empty(tag) => {
+                let prefix = format!("{}{}.", path.iter().map(|name| name.to_owned() + ".").collect::<String>(), std::str::from_utf8(tag.name().as_ref())?);
+                insert_attrs(&mut values, &prefix, &tag)?;
+            },
+            Event::Text(text) => {
+                values.insert(path.join("."), text.unescape()?.into_owned());
+            },
+            Event::End(_) if path.pop().is_none() => break,
             _ => (),
         }
+        buf.clear();
+    }
+
+    let Some(area_id) = values.get("Area.ID") else {
+        return Ok(None);
+    };
+    let side = match values.get("Area.Mode").map(String::as_str) {
+        Some("Normal" | "0") | None => Side::A,
+        Some("BSide" | "1") => Side::B,
+        Some("CSide" | "2") => Side::C,
+        Some(mode) => return Err(anyhow!("invalid area mode in current session: '{}'", mode)),
+    };
+    let chapter = match values.get("Area.SID") {
+        Some(sid) => Chapter::from_sid(sid, side)?,
+        None => vanilla_chapter(area_id, side)?,
+    };
+    let respawn_point = match (values.get("RespawnPoint.X"), values.get("RespawnPoint.Y")) {
+        (Some(x), Some(y)) => Some((x.parse::<f32>()? as i32, y.parse::<f32>()? as i32)),
+        _ => None,
+    };
+
+    Ok(Some(Session {
+        chapter,
+        room: values.get("Level").cloned().unwrap_or_default(),
+        respawn_point,
+        time: values.get("Time").map(|time| parse_ticks(time)).transpose()?.unwrap_or_default(),
+        deaths: values.get("Deaths").map(|deaths| deaths.parse()).transpose()?.unwrap_or_default(),
+    }))
+}
+
+/// The vanilla chapter with an area ID from the save. Everest numbers the areas of every level set
+/// together, so the IDs of modded areas can go far above the vanilla ones. Those have an SID, which
+/// is used instead
+fn vanilla_chapter(area_id: &str, side: Side) -> anyhow::Result<Chapter> {
+    let index: u32 = area_id.parse().with_context(|| format!("invalid area ID '{}'", area_id))?;
+    let index = u8::try_from(index).map_err(|_| anyhow!("Invalid chapter index: {}", index))?;
+    Chapter::from_index(index, side)
+}
+
+/// Inserts every attribute of a tag into a map, with the attribute names prefixed by the prefix
+fn insert_attrs(values: &mut HashMap<String, String>, prefix: &str, tag: &BytesStart) -> anyhow::Result<()> {
+    for attr in tag.attributes() {
+        let attr = attr?;
+        values.insert(prefix.to_owned() + std::str::from_utf8(attr.key.as_ref())?, attr.unescape_value()?.into_owned());
     }
-    buf.clear();
-    Ok(data)
+    Ok(())
+}
+
+/// Converts a time stored in the save file, which is in ticks of 100ns, into a duration
+fn parse_ticks(ticks: &str) -> anyhow::Result<Duration> {
+    Ok(Duration::from_micros(ticks.parse::<u64>()?/10))
 }
 
 fn find_attr(name: &[u8], tag: &BytesStart) -> anyhow::Result<String> {
@@ -75,8 +509,169 @@ fn find_attr(name: &[u8], tag: &BytesStart) -> anyhow::Result<String> {
     });
 
     if let Some(attr) = attr {
-        return Ok(std::str::from_utf8(attr?.value.as_ref())?.to_owned());
+        Ok(std::str::from_utf8(attr?.value.as_ref())?.to_owned())
     } else {
-        return Err(anyhow!("Could not find attribute {} on tag {}", std::str::from_utf8(name).unwrap(), std::str::from_utf8(tag.name().as_ref()).unwrap()));
+        Err(anyhow!("Could not find attribute {} on tag {}", std::str::from_utf8(name).unwrap(), std::str::from_utf8(tag.name().as_ref()).unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: u64 = 10_000_000;
+
+    /// The stats of a chapter side, with its checkpoints in the way the game writes them
+    fn mode(time_played: u64, best_time: u64, checkpoints: &[&str]) -> String {
+        let checkpoints = match checkpoints {
+            [] => "<Checkpoints />".to_owned(),
+            rooms => format!("<Checkpoints>{}</Checkpoints>", rooms.iter().map(|room| format!("<string>{}</string>", room)).collect::<String>()),
+        };
+        format!(
+            r#"<AreaModeStats TotalStrawberries="1" Completed="{}" SingleRunCompleted="false" FullClear="false" Deaths="3" TimePlayed="{}" BestTime="{}" BestFullClearTime="0" BestDashes="0" BestDeaths="0" HeartGem="false"><Strawberries /><BestSpeedrunTimes />{}</AreaModeStats>"#,
+            best_time > 0, time_played, best_time, checkpoints,
+        )
+    }
+
+    fn area(id: u32, sid: Option<&str>, a_side: &str) -> String {
+        let sid = sid.map(|sid| format!(r#" SID="{}""#, sid)).unwrap_or_default();
+        format!(r#"<AreaStats ID="{}" Cassette="false"{}><Modes>{}{}{}</Modes></AreaStats>"#, id, sid, a_side, mode(0, 0, &[]), mode(0, 0, &[]))
+    }
+
+    fn vanilla_save() -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<SaveData xmlns:xsd="http://www.w3.org/2001/XMLSchema">
+  <Version>1.4.0.0</Version>
+  <Name>Madeline</Name>
+  <Time>{file_time}</Time>
+  <Areas>
+    {prologue}
+    {city}
+    {site}
+  </Areas>
+  <CurrentSession Level="9b" FirstLevel="false" InArea="true" Time="{session_time}" Deaths="4">
+    <Area ID="2" Mode="Normal" />
+    <RespawnPoint><X>160.5</X><Y>-24</Y></RespawnPoint>
+    <Flags><string>oshiro_clutter</string></Flags>
+    <Time>{session_time}</Time>
+  </CurrentSession>
+</SaveData>
+"#,
+            file_time = 500 * SECOND,
+            session_time = 42 * SECOND,
+            prologue = area(0, None, &mode(30 * SECOND, 25 * SECOND, &[])),
+            city = area(1, None, &mode(200 * SECOND, 180 * SECOND, &["6", "9b"])),
+            site = area(2, None, &mode(60 * SECOND, 0, &["3"])),
+        )
+    }
+
+    fn everest_save() -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<SaveData>
+  <Time>{file_time}</Time>
+  <Areas>
+    {prologue}
+  </Areas>
+  <LevelSets>
+    <LevelSetStats Name="StrawberryJam2021">
+      <Areas>
+        {custom}
+      </Areas>
+    </LevelSetStats>
+  </LevelSets>
+  <LevelSetRecycleBin>
+    <LevelSetStats Name="Uninstalled">
+      <Areas>
+        {uninstalled}
+      </Areas>
+    </LevelSetStats>
+  </LevelSetRecycleBin>
+  <CurrentSession_Safe Level="a-01" Time="{session_time}" Deaths="0">
+    <Area ID="300" Mode="BSide" SID="StrawberryJam2021/1-Beginner/maya" />
+  </CurrentSession_Safe>
+</SaveData>
+"#,
+            file_time = 90 * SECOND,
+            session_time = 5 * SECOND,
+            prologue = area(0, Some("Celeste/0-Intro"), &mode(30 * SECOND, 30 * SECOND, &[])),
+            custom = area(300, Some("StrawberryJam2021/1-Beginner/maya"), &mode(50 * SECOND, 45 * SECOND, &["a-03"])),
+            uninstalled = area(301, Some("Uninstalled/1/old"), &mode(20 * SECOND, 20 * SECOND, &[])),
+        )
+    }
+
+    #[test]
+    fn parses_a_vanilla_save() {
+        let save = parse_save(vanilla_save().as_bytes()).unwrap();
+        assert_eq!(save.file_time, Duration::from_secs(500));
+
+        let chapters: HashSet<&Chapter> = save.areas.keys().collect();
+        assert_eq!(chapters, HashSet::from([&Chapter::Prologue, &Chapter::City(Side::A), &Chapter::Site(Side::A)]));
+        let city = &save.areas[&Chapter::City(Side::A)];
+        assert_eq!(city.time_played, Duration::from_secs(200));
+        assert_eq!(city.best_time, Duration::from_secs(180));
+        assert_eq!((city.deaths, city.total_strawberries, city.completed), (3, 1, true));
+        assert_eq!(city.checkpoints, ["6", "9b"]);
+        assert!(!save.areas[&Chapter::Site(Side::A)].completed);
+
+        assert_eq!(save.current_session, Some(Session {
+            chapter: Chapter::Site(Side::A),
+            room: "9b".to_owned(),
+            respawn_point: Some((160, -24)),
+            time: Duration::from_secs(42),
+            deaths: 4,
+        }));
+    }
+
+    #[test]
+    fn time_map_only_has_completed_chapters() {
+        let save = parse_save(vanilla_save().as_bytes()).unwrap();
+        let times = save.time_map(TimingSource::TimePlayed);
+        assert_eq!(times.len(), 2);
+        assert_eq!(times[&Split::Chapter(Chapter::City(Side::A))], Duration::from_secs(200));
+        assert_eq!(save.time_map(TimingSource::BestTime)[&Split::Chapter(Chapter::Prologue)], Duration::from_secs(25));
+        assert_eq!(save.checkpoint_map().len(), 3);
+    }
+
+    #[test]
+    fn parses_an_everest_save() {
+        let save = parse_save(everest_save().as_bytes()).unwrap();
+        assert_eq!(save.file_time, Duration::from_secs(90));
+
+        let maya = Chapter::Custom("StrawberryJam2021/1-Beginner/maya".to_owned(), Side::A);
+        let chapters: HashSet<&Chapter> = save.areas.keys().collect();
+        assert_eq!(chapters, HashSet::from([&Chapter::Prologue, &maya]));
+        assert_eq!(save.areas[&maya].checkpoints, ["a-03"]);
+
+        let session = save.current_session.unwrap();
+        assert_eq!(session.chapter, Chapter::Custom("StrawberryJam2021/1-Beginner/maya".to_owned(), Side::B));
+        assert_eq!((session.room.as_str(), session.time, session.respawn_point), ("a-01", Duration::from_secs(5), None));
+    }
+
+    #[test]
+    fn a_save_without_a_session_has_none() {
+        let save = vanilla_save();
+        let start = save.find("  <CurrentSession").unwrap();
+        let end = save.find("</CurrentSession>\n").unwrap() + "</CurrentSession>\n".len();
+        let save = parse_save(format!("{}{}", &save[..start], &save[end..]).as_bytes()).unwrap();
+        assert_eq!(save.current_session, None);
+        assert_eq!(save.file_time, Duration::from_secs(500));
+    }
+
+    #[test]
+    fn a_truncated_save_is_an_error() {
+        let save = vanilla_save();
+        for cut in [save.find("<Areas>").unwrap(), save.find("<CurrentSession").unwrap() + 40, save.len() - 20] {
+            assert!(parse_save(&save.as_bytes()[..cut]).is_err(), "cut at {}", cut);
+        }
+    }
+
+    #[test]
+    fn area_ids_without_an_sid_are_vanilla_chapters() {
+        assert_eq!(vanilla_chapter("7", Side::B).unwrap(), Chapter::Summit(Side::B));
+        assert!(vanilla_chapter("300", Side::A).is_err());
+        assert!(vanilla_chapter("-1", Side::A).is_err());
+        assert!(vanilla_chapter("10", Side::B).is_err());
     }
 }
@@ -1,7 +1,8 @@
 use std::time::Duration;
 use crossterm::style::Color;
 
-use crate::{levels::Chapter, saves::TimeMap};
+use crate::{comparison::Comparison, saves::TimingSource};
+
 
 #[derive(Clone)]
 pub struct TableCell {
@@ -42,32 +43,26 @@ pub struct Table {
 impl Table {
     pub fn from_header(columns: Vec<(&str, u16)>) -> Self {
         Self {
-            columns: columns.iter().map(|(text, width)| TableColumn { width: *width, cells: vec![TableCell::new_default(*text)]}).collect(),
+            columns: columns.iter().map(|(text, width)| TableColumn { width: (*width).max(text.chars().count() as u16), cells: vec![TableCell::new_default(text)]}).collect(),
         }
     }
 
-    pub fn from_default_header() -> Self {
-         Table::from_header(vec![("Chapter", 16), ("Time", 7), ("Diff", 5)])
-    }
-
-    pub fn from_times(times: &TimeMap, route: &[Chapter]) -> Self {
-        let mut table = Self::from_default_header();
-        for chapter in route {
-            let duration_str = if let Some(time) = times.get(chapter) {
-                // TODO: format this properly
-                format!("{:?}", time)
-            } else {
-                "-".to_owned()
-            };
-            table.push_row(vec![TableCell::new_default(chapter.to_string().as_str()), TableCell::new_default(duration_str.as_str()), TableCell::new_default("-")])
+    /// The default header, with the time column named after the timing source in use, followed
+    /// by the real time column and the difference to the active comparison. The possible time
+    /// save column comes last, if it is shown
+    pub fn from_default_header(timing_source: TimingSource, comparison: Comparison, possible_time_save: bool) -> Self {
+        let mut columns = vec![("Chapter", 16), (timing_source.name(), 7), ("RTA", 7), (comparison.name(), 5)];
+        if possible_time_save {
+            columns.push(("Save", 5));
         }
-        table
+        Table::from_header(columns)
     }
 
     pub fn push_row(&mut self, cells: Vec<TableCell>) {
         assert!(cells.len() == self.columns.len(), "tried to push row of incorrect size. expected {}, but got {}", self.columns.len(), cells.len());
 
         for (cell, col) in cells.iter().zip(self.columns.iter_mut()) {
+            col.width = col.width.max(cell.text.chars().count() as u16);
             col.cells.push(cell.clone());
         }
     }
@@ -75,6 +70,16 @@ impl Table {
     pub fn columns(&self) -> &Vec<TableColumn> {
         &self.columns
     }
+
+    /// The number of characters the table takes up horizontally
+    pub fn width(&self) -> u16 {
+        self.columns.iter().map(|col| col.width + 2).sum()
+    }
+
+    /// The number of rows in the table, including the header
+    pub fn height(&self) -> u16 {
+        self.columns.first().map_or(0, |col| col.cells.len() as u16)
+    }
 }
 
 pub struct TableColumn {
@@ -88,7 +93,7 @@ impl TableColumn {
     }
 }
 
-fn format_duration(duration: &Duration) -> String {
+pub fn format_duration(duration: &Duration) -> String {
     let secs_total = duration.as_secs();
     let mins = secs_total / 60;
     let secs = secs_total % 60;
@@ -52,6 +52,23 @@ impl Terminal {
         self.stdout.flush().context("could not flush stdout while writing table column")
     }
 
+    /// Writes text at a position, without clearing anything around it
+    pub fn write_at(&mut self, text: &str, color: Color, x: u16, y: u16) -> Result<()> {
+        self.queue_write(text, color, x, y)?.flush().context("could not flush stdout while writing text")
+    }
+
+    /// Replaces the contents of a line with the given text
+    pub fn write_line(&mut self, text: &str, color: Color, y: u16) -> Result<()> {
+        self.stdout.queue(MoveTo(self.offset_x, y + self.offset_y))?.queue(Clear(ClearType::CurrentLine)).context("could not clear the current line")?;
+        self.queue_write(text, color, 0, y)?.flush().context("could not flush stdout while writing line")
+    }
+
+    /// Clears the whole screen
+    pub fn clear(&mut self) -> Result<()> {
+        self.stdout.execute(Clear(ClearType::All)).context("could not clear the screen")?;
+        Ok(())
+    }
+
     pub fn write_status(&mut self, text: &str, color: Color) -> Result<()> {
         self.queue_clear_status()?;
         self.queue_write_raw(text, color, 0, crossterm::terminal::size()?.1-1)?.flush().context("could not flush stdout while writing status")
@@ -1,83 +1,192 @@
-use std::{path::Path, sync::Mutex, collections::HashMap, time::Duration, fs::File};
+use std::{path::{Path, PathBuf}, sync::Mutex, collections::{HashMap, HashSet}, time::{Duration, Instant}, fs::File};
 
 use anyhow::{anyhow, Result};
-use futures::{StreamExt, select, future::FutureExt};
-use crossterm::event::{EventStream, Event, KeyCode};
+use futures::{StreamExt, select, future::FutureExt, channel::mpsc::{channel, Receiver}, SinkExt};
+use crossterm::{event::{EventStream, Event, KeyCode}, style::Color};
 
-use crate::{watch::AsyncWatcher, terminal::Terminal, table::{Table, TableCell}, levels::Chapter, saves::TimeMap};
+use crate::{watch::{AsyncWatcher, WatchEvent, WatchOptions}, terminal::Terminal, table::{Table, TableCell, format_duration}, levels::{Chapter, Route, Split}, saves::{self, TimeMap, SaveStats, SaveTotals, TimingSource}, config::Config, record::Recorder, bindings::{self, Action, Bindings}, clock::Clock, run::{Run, RunState, Transition}, history::{Attempt, AttemptSplit, History}, comparison::Comparison};
 
-const PB_PATH: &str = "pb.json";
-const BEST_SPLITS_PATH: &str = "best_splits.json";
+const SPLITS_DIR: &str = "splits";
+const PB_FILE: &str = "pb.json";
+const BEST_SPLITS_FILE: &str = "best_splits.json";
+const PB_RTA_FILE: &str = "pb_rta.json";
+const HISTORY_FILE: &str = "history.jsonl";
+
+/// How often the real time is redrawn while the clock is running
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
 
 pub struct Timer {
     watcher: AsyncWatcher,
     terminal: Mutex<Terminal>,
-    current_save: Option<TimeMap>,
+    route: Route,
+    timing_source: TimingSource,
+    /// The directory of the save files. This is none when a recording is replayed
+    save_dir: Option<PathBuf>,
+    save_slot: u32,
+    watch_options: WatchOptions,
+    recorder: Option<Recorder>,
+    current_save: Option<SaveStats>,
+    /// When the current save was seen being written. The game only saves the time of the current
+    /// session every now and then, so it keeps running from here until the next save. This is
+    /// none until the first save update, since the save might be from long before rcas started
+    saved_at: Option<Instant>,
+    /// The time in their chapter at which checkpoints were first seen in the save. Checkpoints
+    /// that were already reached when rcas started are not included, since it is unknown when
+    /// they were reached
+    checkpoint_times: TimeMap,
+    /// The file time at which each split was first seen in the save. Like with checkpoints, splits
+    /// that were already reached when rcas started are not included
+    file_times: TimeMap,
+    /// The file time when rcas started watching the current attempt. Splits that were reached
+    /// before then fall back to the time played, so the file time of the splits after them is
+    /// counted from here
+    file_time_start: Duration,
+    /// The lifecycle of the current attempt
+    run: Run,
+    /// Splits that were undone in the current attempt. They are counted again once the save shows
+    /// them being reached again
+    undone: HashSet<Split>,
+    /// The save when the attempt was reset by hand. Chapters that have not changed since then are
+    /// not counted in the current attempt
+    attempt_start: Option<SaveStats>,
+    /// Whether save updates are held back until the timer is unpaused
+    paused: bool,
+    paused_saves: Vec<SaveStats>,
+    bindings: Bindings,
+    show_help: bool,
+    /// Whether the sum of best, best possible time and possible time saves are shown
+    show_stats: bool,
+    /// The real time of the current attempt
+    clock: Clock,
+    /// The real time at which each split of the current attempt was reached
+    rta_times: TimeMap,
     pb: TimeMap,
+    /// The real time of each split in the personal best
+    pb_rta: TimeMap,
+    /// The PB and its real times that the finished attempt replaced, so that undoing its final
+    /// split gives them back
+    previous_pb: Option<(TimeMap, TimeMap)>,
     best_splits: TimeMap,
+    /// The splits that were golds in the current attempt, along with the best split they replaced
+    golds: HashMap<Split, Option<Duration>>,
+    /// Every earlier attempt at the route with the current timing source
+    history: History,
+    comparison: Comparison,
 }
 
 impl Timer {
-    pub fn new() -> Result<Self> {
-        let path_str = shellexpand::full("$XDG_DATA_HOME/Celeste/Saves/2.celeste")?;
-        let path_str = path_str.as_ref();
-        let path = Path::new(path_str);
-        let current_save = crate::saves::load_save(path).ok();
+    pub fn new(config: Config) -> Result<Self> {
+        let Config { route, timing_source, save_slot, save_dir, watch: watch_options, record, replay, replay_speed, bindings, show_stats } = config;
+        // A replay brings its own saves, so the game does not need to be installed. It starts from
+        // the save that was there when it was recorded, like rcas did back then
+        let (watcher, save_dir, save_slot, current_save) = if let Some(replay) = replay {
+            let (watcher, initial) = AsyncWatcher::from_replay(&replay, replay_speed)?;
+            (watcher, None, 0, initial)
+        } else {
+            let save_dir = saves::find_save_dir(save_dir.as_deref())?;
+            let save_slot = match save_slot {
+                Some(slot) => slot,
+                None => saves::latest_slot(&save_dir)?,
+            };
+            let path = saves::slot_path(&save_dir, save_slot);
+            let current_save = saves::load_save(&path).ok();
+            let watcher = AsyncWatcher::new(&path, watch_options)?;
+            (watcher, Some(save_dir), save_slot, current_save)
+        };
 
-        let watcher = AsyncWatcher::new(path)?;
+        let mut recorder = record.map(|path| Recorder::new(&path)).transpose()?;
+        // The save that was there when rcas started is recorded as well, so that replays start
+        // from the same state
+        if let (Some(recorder), Some(save)) = (recorder.as_mut(), current_save.as_ref()) {
+            recorder.record_initial(save)?;
+        }
 
         let terminal = Mutex::new(Terminal::new()?);
 
-        let pb_reader = File::open(PB_PATH);
-        let pb = if let Ok(reader) = pb_reader {
-            serde_json::from_reader(reader).unwrap_or_else(|_| {
-                terminal.lock().unwrap().write_error("could not deserialize pb from file. initializing empty pb").unwrap();
-                HashMap::new()
-            })
-        } else {
-            terminal.lock().unwrap().write_status_default("could not open pb file. initializing empty pb").unwrap();
-            HashMap::new()
-        };
+        migrate_legacy_splits(&terminal, &route, timing_source);
+        let pb = load_time_map(&terminal, &splits_path(&route, timing_source, PB_FILE), "pb");
+        let mut best_splits = load_time_map(&terminal, &splits_path(&route, timing_source, BEST_SPLITS_FILE), "best splits");
+        let pb_rta = load_time_map(&terminal, &splits_path(&route, timing_source, PB_RTA_FILE), "pb real time");
+        seed_golds(&mut best_splits, &pb);
+        let history = load_history(&terminal, &splits_path(&route, timing_source, HISTORY_FILE));
 
-        let best_splits_reader = File::open(BEST_SPLITS_PATH);
-        let best_splits = if let Ok(reader) = best_splits_reader {
-            serde_json::from_reader(reader).unwrap_or_else(|_| {
-                terminal.lock().unwrap().write_error("could not deserialize best splits from file. initializing empty best splits").unwrap();
-                HashMap::new()
-            })
-        } else {
-            terminal.lock().unwrap().write_status_default("could not open best splits file. initializing empty best splits").unwrap();
-            HashMap::new()
-        };
+        let run = Run::new(&route);
+        let file_time_start = current_save.as_ref().map_or(Duration::ZERO, |save| save.file_time);
 
         Ok(Self {
             watcher,
             terminal,
+            route,
+            timing_source,
+            save_dir,
+            save_slot,
+            watch_options,
+            recorder,
             current_save,
+            saved_at: None,
+            checkpoint_times: HashMap::new(),
+            file_times: HashMap::new(),
+            file_time_start,
+            run,
+            undone: HashSet::new(),
+            attempt_start: None,
+            paused: false,
+            paused_saves: Vec::new(),
+            bindings,
+            show_help: false,
+            show_stats,
+            clock: Clock::default(),
+            rta_times: HashMap::new(),
             pb,
+            pb_rta,
+            previous_pb: None,
             best_splits,
+            golds: HashMap::new(),
+            history,
+            comparison: Comparison::default(),
         })
     }
 
     pub fn run(mut self) -> Result<()> {
         futures::executor::block_on(async {
             let mut key_reader = EventStream::new();
-            if let Err(e) = self.on_save_update(&crate::levels::ANY_PERCENT_ROUTE) {
+            let mut ticks = ticker(TICK_INTERVAL);
+            if let Err(e) = self.mark_done_before_attempt() {
                 self.terminal.lock().unwrap().write_error(format!("an error occurred: {:?}", e).as_str()).unwrap();
             }
+            if self.save_dir.is_some() {
+                self.terminal.lock().unwrap().write_status_default(&format!("watching save slot {}", self.save_slot)).unwrap();
+            }
             loop {
                 let rx = &mut self.watcher.watcher_rx;
                 let mut recv = rx.next().fuse();
                 let mut key_event = key_reader.next().fuse();
+                let mut tick = ticks.next().fuse();
                 select! {
-                    data = recv => {
-                        if let Some(data) = data {
-                            self.current_save = Some(data);
-                            if let Err(e) = self.on_save_update(&crate::levels::ANY_PERCENT_ROUTE) {
+                    _ = tick => {
+                        if (self.clock.is_running() || self.session_running()) && !self.paused {
+                            if let Err(e) = self.print_times() {
                                 self.terminal.lock().unwrap().write_error(format!("an error occurred: {:?}", e).as_str()).unwrap();
                             }
-                        } else {
-                            break;
+                        }
+                    },
+                    event = recv => {
+                        match event {
+                            Some(WatchEvent::SaveUpdated(data)) => {
+                                if let Some(Err(e)) = self.recorder.as_mut().map(|recorder| recorder.record(&data)) {
+                                    self.terminal.lock().unwrap().write_error(format!("could not record save update: {:#}", e).as_str()).unwrap();
+                                }
+                                if self.paused {
+                                    self.paused_saves.push(data);
+                                } else {
+                                    self.apply_save(data);
+                                }
+                            },
+                            Some(WatchEvent::ParseError(e)) => {self.terminal.lock().unwrap().write_error(format!("unable to load save data: {:#}", e).as_str()).unwrap();},
+                            Some(WatchEvent::WatcherError(e)) => {self.terminal.lock().unwrap().write_error(format!("{:#}", e).as_str()).unwrap();},
+                            Some(WatchEvent::FileMissing(path)) => {self.terminal.lock().unwrap().write_error(format!("save file {} does not exist", path.display()).as_str()).unwrap();},
+                            Some(WatchEvent::ReplayFinished) => {self.terminal.lock().unwrap().write_status_default("the replay has finished").unwrap();},
+                            None => break,
                         }
                     },
                     maybe_event = key_event => {
@@ -95,102 +204,712 @@ impl Timer {
                     }
                 }
             }
+            // An attempt that is still running might be continued after a restart, so only
+            // finished attempts are added to the history on quit
+            if self.run.state() == RunState::Finished {
+                self.record_attempt().unwrap();
+            }
             self.save_data().unwrap();
         });
         Ok(())
     }
 
-    fn on_save_update(&mut self, route: &[Chapter]) -> Result<()> {
+    /// Makes a new save the current one
+    fn apply_save(&mut self, data: SaveStats) {
+        let result = (|| {
+            if self.current_save.as_ref().is_some_and(|previous| data.is_reset_of(previous)) {
+                self.new_attempt()?;
+            } else if matches!(self.run.state(), RunState::NoAttempt | RunState::Reset) {
+                // The attempt starts once the game is played, either for the first time since rcas
+                // started or after a reset by hand
+                self.transition(Transition::Start)?;
+            }
+            self.record_new_splits(&data);
+            self.current_save = Some(data);
+            self.saved_at = Some(Instant::now());
+            self.on_save_update()
+        })();
+        if let Err(e) = result {
+            self.terminal.lock().unwrap().write_error(format!("an error occurred: {:?}", e).as_str()).unwrap();
+        }
+    }
 
-        self.print_times(route)?;
+    /// Splits on every split of the route that the save shows as reached for the first time, and
+    /// finishes the attempt once every split is done
+    fn on_save_update(&mut self) -> Result<()> {
+        let data = self.split_times()?;
+        let reached: Vec<Split> = self.route.splits.iter()
+            .map(|route_split| &route_split.split)
+            .filter(|split| data.contains_key(split) && !self.run.is_done(split))
+            .cloned()
+            .collect();
+        for split in reached {
+            self.transition(Transition::Split(split))?;
+        }
+        if self.run.can_finish() {
+            self.transition(Transition::Finish)?;
+        }
 
-        let data = self.current_save.as_ref().ok_or(anyhow!("no current save!"))?;
-        let TimeTotals { total_time, pb_total, .. } = self.get_time_totals(route);
-        let mut term = self.terminal.lock().unwrap();
+        self.print_times()
+    }
 
-        if data.len() >= route.len() {
-            if total_time <= pb_total {
-                term.write_status("new personal best! congratulations!", crossterm::style::Color::Green)?;
-                self.pb = data.clone();
+    /// Marks the splits that the save already shows as reached, before any attempt was seen, as
+    /// done. They belong to an attempt that started before rcas did, so they are not split on
+    fn mark_done_before_attempt(&mut self) -> Result<()> {
+        if self.current_save.is_some() {
+            for split in self.split_times()?.keys() {
+                self.run.mark_done_before(split);
             }
-            for chapter in route {
-                let time = data.get(chapter).ok_or(anyhow!("could not get time for chapter {}", chapter.to_string()))?;
-                if let Some(best_split) = self.best_splits.get(chapter) {
-                    if time < best_split {
-                        self.best_splits.insert(chapter.clone(), time.clone());
-                    }
+        }
+        self.print_times()
+    }
+
+    /// Moves the attempt along, and does everything that comes with the transition. This is the
+    /// only place where the PB and best splits are updated
+    fn transition(&mut self, transition: Transition) -> Result<()> {
+        // Skipped splits have no times to take back
+        let was_skipped = matches!(&transition, Transition::Undo(split) if self.run.is_skipped(split));
+        let was_finished = self.run.state() == RunState::Finished;
+        // The attempt is added to the history before the reset forgets its splits
+        if transition == Transition::Reset {
+            self.record_attempt()?;
+        }
+        self.run.apply(&transition)?;
+        match transition {
+            Transition::Start => {
+                self.clock.start();
+                self.terminal.lock().unwrap().write_status_default("started a new attempt")?;
+            },
+            Transition::Split(split) => {
+                if let Some(now) = self.clock.elapsed().filter(|_| self.clock.is_running()) {
+                    self.rta_times.insert(split.clone(), now);
                 }
-            }
+                self.record_gold(split)?;
+            },
+            Transition::Skip(split) => {
+                self.terminal.lock().unwrap().write_status_default(&format!("skipped {}", split.display_name()))?;
+            },
+            Transition::Undo(split) => {
+                if !was_skipped {
+                    self.checkpoint_times.remove(&split);
+                    self.file_times.remove(&split);
+                    self.rta_times.remove(&split);
+                    self.undone.insert(split.clone());
+                }
+                // A gold that is taken back gives the old best split back
+                match self.golds.remove(&split) {
+                    Some(Some(previous)) => {self.best_splits.insert(split.clone(), previous);},
+                    Some(None) => {self.best_splits.remove(&split);},
+                    None => {},
+                }
+                // The final split might have been undone, so the attempt is not finished anymore,
+                // and any PB it set is taken back
+                if let (true, Some((pb, pb_rta))) = (was_finished, self.previous_pb.take()) {
+                    self.pb = pb;
+                    self.pb_rta = pb_rta;
+                    self.save_data()?;
+                }
+                self.clock.unstop();
+                self.terminal.lock().unwrap().write_status_default(&format!("undid {}", split.display_name()))?;
+            },
+            Transition::Finish => {
+                self.clock.stop();
+                self.finish_attempt()?;
+            },
+            Transition::Reset => {
+                self.save_data()?;
+                self.clear_attempt();
+            },
+        }
+        Ok(())
+    }
+
+    /// Adds the current attempt to the history, if there is one. Only attempts that were started
+    /// and split on while rcas was running are added, so that the same attempt is not added again
+    /// every time rcas starts on its save
+    fn record_attempt(&mut self) -> Result<()> {
+        let (RunState::Running | RunState::Finished, Some(started), true) = (self.run.state(), self.run.started(), self.run.has_splits()) else {
+            return Ok(());
+        };
+        let times = self.split_times()?;
+        let rta_times = self.rta_split_times();
+        let splits = self.route.splits.iter().map(|route_split| AttemptSplit {
+            split: route_split.split.clone(),
+            time: times.get(&route_split.split).copied(),
+            rta: rta_times.get(&route_split.split).copied(),
+            skipped: self.run.is_skipped(&route_split.split),
+        }).collect();
+        // Deaths from before a reset by hand belong to the earlier attempts
+        let deaths_before = self.attempt_start.as_ref().map_or(0, |save| save.totals().deaths);
+        let deaths = self.current_save.as_ref().map_or(0, |save| save.totals().deaths).saturating_sub(deaths_before);
+        let finished = self.run.state() == RunState::Finished;
+        let attempt = Attempt {
+            started,
+            route: self.route.name.clone(),
+            splits,
+            deaths,
+            finished,
+            reset_at: if finished { None } else { self.run.next_split().cloned() },
+        };
+
+        if self.is_replay() {
+            self.history.attempts.push(attempt);
+            return Ok(());
         }
+        std::fs::create_dir_all(splits_path(&self.route, self.timing_source, ""))?;
+        self.history.append(attempt)
+    }
+
+    /// Makes the time of a split that was just reached its best split, if it is faster than the
+    /// best split so far, or if there is none yet. This happens right away, so that attempts that
+    /// are reset later still count
+    fn record_gold(&mut self, split: Split) -> Result<()> {
+        let Some(time) = self.split_times()?.get(&split).copied() else {
+            return Ok(());
+        };
+        let previous = self.best_splits.get(&split).copied();
+        if previous.is_some_and(|previous| previous <= time) {
+            return Ok(());
+        }
+        self.best_splits.insert(split.clone(), time);
+        self.golds.insert(split, previous);
         self.save_data()
+    }
+
+    /// Updates the PB with the attempt that was just finished. Attempts with skipped splits can't
+    /// be a PB, since some of their times are missing
+    fn finish_attempt(&mut self) -> Result<()> {
+        let data = self.split_times()?;
+        let TimeTotals { total_time, pb_total, .. } = self.get_time_totals();
 
+        if !self.run.has_skips() && !self.run.has_done_before() && (self.pb.is_empty() || total_time < pb_total) {
+            self.terminal.lock().unwrap().write_status("new personal best! congratulations!", Color::Green)?;
+            let pb_rta = self.rta_split_times();
+            self.previous_pb = Some((std::mem::replace(&mut self.pb, data), std::mem::replace(&mut self.pb_rta, pb_rta)));
+        } else {
+            self.terminal.lock().unwrap().write_status_default("finished the attempt")?;
+        }
+        self.save_data()
     }
 
-    fn print_times(&mut self, route: &[Chapter]) -> Result<()> {
+    fn print_times(&self) -> Result<()> {
         let mut term = self.terminal.lock().unwrap();
-        let mut table = Table::from_default_header();
+        let mut table = Table::from_default_header(self.timing_source, self.comparison, self.show_stats);
 
-        let TimeTotals { total_time, pb_total_running, .. } = self.get_time_totals(route);
-        let data = self.current_save.as_ref().ok_or(anyhow!("no current save!"))?;
+        let TimeTotals { total_time, comparison_total_running, sum_of_best, best_possible_time, .. } = self.get_time_totals();
+        let data = self.split_times()?;
+        let rta = self.rta_split_times();
+        let comparison_times = self.comparison_times();
 
-        for chapter in route {
-            if let Some(run_time) = data.get(chapter) {
-                let pb_time = self.pb.get(chapter);
-                let best_split_time = self.best_splits.get(chapter);
+        for route_split in &self.route.splits {
+            if !data.contains_key(&route_split.split) && self.run.is_skipped(&route_split.split) {
+                let mut row = vec![TableCell::new_default(&route_split.display_name()), TableCell::new_default("-"), TableCell::new_default("-"), TableCell::new_default("-")];
+                if self.show_stats {
+                    row.push(self.possible_time_save(&route_split.split).map_or(TableCell::new_default("-"), |save| TableCell::from_duration(&save)));
+                }
+                table.push_row(row);
+            }
+            if let Some(run_time) = data.get(&route_split.split) {
+                let comparison_time = comparison_times.get(&route_split.split);
+                let is_gold = self.golds.contains_key(&route_split.split);
 
-                let chapter_cell = TableCell::new_default(chapter.to_string().as_str());
+                let chapter_cell = TableCell::new_default(&route_split.display_name());
                 let split_time_cell = TableCell::from_duration(run_time);
-                let diff_cell = if let Some(pb_time) = pb_time {
-                    TableCell::from_diff(pb_time, run_time, run_time < best_split_time.unwrap_or(&Duration::MAX))
+                let rta_cell = rta.get(&route_split.split).map_or(TableCell::new_default("-"), TableCell::from_duration);
+                let diff_cell = if let Some(comparison_time) = comparison_time {
+                    TableCell::from_diff(comparison_time, run_time, is_gold)
                 } else {
-                    TableCell::new_default("-")
+                    TableCell { text: "-".to_owned(), color: if is_gold { Color::Blue } else { Color::Reset } }
                 };
-                table.push_row(vec![chapter_cell, split_time_cell, diff_cell]);
+                let mut row = vec![chapter_cell, split_time_cell, rta_cell, diff_cell];
+                if self.show_stats {
+                    row.push(self.possible_time_save(&route_split.split).map_or(TableCell::new_default("-"), |save| TableCell::from_duration(&save)));
+                }
+                table.push_row(row);
             }
         }
-        table.push_row(vec![TableCell::new_default("Total"), TableCell::from_duration(&total_time), TableCell::from_diff(&pb_total_running, &total_time, false)]);
-        term.write_table(&table)
+        let rta_total_cell = self.clock.elapsed().map_or(TableCell::new_default("-"), |rta_total| TableCell::from_duration(&rta_total));
+        let total_diff_cell = if comparison_times.is_empty() {
+            TableCell::new_default("-")
+        } else {
+            TableCell::from_diff(&comparison_total_running, &total_time, false)
+        };
+        let mut total_row = vec![TableCell::new_default("Total"), TableCell::from_duration(&total_time), rta_total_cell, total_diff_cell];
+        if self.show_stats {
+            let total_save: Duration = self.route.splits.iter().filter_map(|route_split| self.possible_time_save(&route_split.split)).sum();
+            total_row.push(TableCell::from_duration(&total_save));
+        }
+        table.push_row(total_row);
+        if self.show_stats {
+            for (name, time) in [("Sum of Best", sum_of_best), ("Best Possible", best_possible_time)] {
+                let mut row = vec![TableCell::new_default(name), time.map_or(TableCell::new_default("-"), |time| TableCell::from_duration(&time))];
+                row.extend(std::iter::repeat_n(TableCell::new_default(""), 3));
+                table.push_row(row);
+            }
+        }
+        term.write_table(&table)?;
+        if self.show_help {
+            self.write_help(&mut term, table.width() + 2)?;
+        }
+
+        if let Some(save) = self.current_save.as_ref() {
+            let SaveTotals { deaths, strawberries, hearts, .. } = save.totals();
+            term.write_line(&format!("Deaths: {}  Strawberries: {}  Hearts: {}", deaths, strawberries, hearts), Color::Reset, table.height() + 1)?;
+            let session_line = match save.current_session.as_ref() {
+                Some(session) => {
+                    let time = session.time + self.saved_at.map_or(Duration::ZERO, |saved_at| saved_at.elapsed());
+                    format!("In {} (room {})  {}  Deaths: {}", session.chapter.display_name(), session.room, format_duration(&time), session.deaths)
+                },
+                None => "Not in a chapter".to_owned(),
+            };
+            term.write_line(&session_line, Color::Reset, table.height() + 2)?;
+        }
+        Ok(())
     }
 
-    fn get_time_totals(&self, route: &[Chapter]) -> TimeTotals {
+    /// Whether the time of the current session is running, which is the case once the game was
+    /// seen saving while in a chapter
+    fn session_running(&self) -> bool {
+        self.saved_at.is_some() && self.current_save.as_ref().is_some_and(|save| save.current_session.is_some())
+    }
+
+    /// Lists the key bindings next to the table
+    fn write_help(&self, term: &mut Terminal, x: u16) -> Result<()> {
+        term.write_at("Keys", Color::Reset, x, 0)?;
+        for (i, action) in Action::ALL.iter().enumerate() {
+            let keys: Vec<String> = self.bindings.keys(*action).iter().map(bindings::key_name).collect();
+            term.write_at(&format!("{:<10} {}", keys.join(" "), action.description()), Color::Reset, x, i as u16 + 1)?;
+        }
+        Ok(())
+    }
+
+    fn get_time_totals(&self) -> TimeTotals {
         let mut total_time = Duration::ZERO;
 
         let mut pb_total = Duration::ZERO;
-        let mut pb_total_running = Duration::ZERO;
+        let mut comparison_total_running = Duration::ZERO;
+        let mut sum_of_best = Some(Duration::ZERO);
+        // The golds of the splits that are still left, on top of the current time
+        let mut golds_left = Some(Duration::ZERO);
 
-        for chapter in route {
-            if let Some(data) = self.current_save.as_ref() {
-                if let Some(time) = data.get(chapter) {
+        let data = self.split_times().ok();
+        let comparison_times = self.comparison_times();
+        for route_split in &self.route.splits {
+            let split = &route_split.split;
+            let gold = self.best_splits.get(split).copied();
+            match data.as_ref().and_then(|data| data.get(split)) {
+                Some(time) => {
                     total_time += *time;
-                    pb_total_running += *self.pb.get(chapter).unwrap_or(&Duration::ZERO);
-                }
+                    comparison_total_running += *comparison_times.get(split).unwrap_or(&Duration::ZERO);
+                },
+                None if !self.run.is_skipped(split) => golds_left = golds_left.zip(gold).map(|(left, gold)| left + gold),
+                None => {},
             }
-            pb_total += *self.pb.get(chapter).unwrap_or(&Duration::ZERO);
+            pb_total += *self.pb.get(split).unwrap_or(&Duration::ZERO);
+            sum_of_best = sum_of_best.zip(gold).map(|(sum, gold)| sum + gold);
+        }
+
+        TimeTotals {
+            total_time,
+            pb_total,
+            comparison_total_running,
+            sum_of_best,
+            best_possible_time: golds_left.map(|left| total_time + left),
         }
+    }
+
+    /// How much faster a split could have been in the PB, going by its best time
+    fn possible_time_save(&self, split: &Split) -> Option<Duration> {
+        Some(self.pb.get(split)?.saturating_sub(*self.best_splits.get(split)?))
+    }
+
+    /// The time of each segment in the active comparison
+    fn comparison_times(&self) -> TimeMap {
+        self.comparison.times(&self.route, &self.pb, &self.best_splits, &self.history)
+    }
+
+    fn switch_comparison(&mut self) -> Result<()> {
+        self.comparison = self.comparison.next();
+        self.redraw()?;
+        self.terminal.lock().unwrap().write_status_default(&format!("comparing against {}", self.comparison.name().to_lowercase()))
+    }
 
-        TimeTotals { total_time, pb_total, pb_total_running }
+    /// Forgets the splits of the previous attempt after the save was started over. Everything in
+    /// the new save has been reached during the new attempt
+    fn new_attempt(&mut self) -> Result<()> {
+        self.transition(Transition::Reset)?;
+        self.current_save = Some(SaveStats::default());
+        self.clear_attempt();
+        self.terminal.lock().unwrap().clear()?;
+        self.transition(Transition::Start)
+    }
+
+    /// Forgets everything about the splits of the current attempt
+    fn clear_attempt(&mut self) {
+        self.checkpoint_times.clear();
+        self.file_times.clear();
+        self.undone.clear();
+        self.golds.clear();
+        self.previous_pb = None;
+        self.attempt_start = None;
+        self.file_time_start = self.current_save.as_ref().map_or(Duration::ZERO, |save| save.file_time);
+        self.clock.reset();
+        self.rta_times.clear();
+    }
+
+    /// The real time of each split since the previous split
+    fn rta_split_times(&self) -> TimeMap {
+        let mut times = HashMap::new();
+        let mut previous = Duration::ZERO;
+        for route_split in &self.route.splits {
+            if let Some(time) = self.rta_times.get(&route_split.split) {
+                times.insert(route_split.split.clone(), time.saturating_sub(previous));
+                previous = *time;
+            }
+        }
+        times
+    }
+
+    /// Records the time of any splits that were not reached in the previous save
+    fn record_new_splits(&mut self, data: &SaveStats) {
+        let Some(previous) = self.current_save.as_ref() else {
+            return;
+        };
+        let previous_checkpoints = previous.checkpoint_map();
+        let mut reached: Vec<(Split, Duration)> = data.checkpoint_map().into_iter().filter(|(split, _)| !previous_checkpoints.contains_key(split)).collect();
+        // Checkpoints that were unlocked before, like when practicing a chapter, are reached again
+        // on every attempt. That only shows in the session, which the game saves in the room of
+        // the checkpoint when it is reached
+        if let Some(session) = data.current_session.as_ref() {
+            let moved = previous.current_session.as_ref().is_none_or(|previous| (&previous.chapter, &previous.room, previous.respawn_point) != (&session.chapter, &session.room, session.respawn_point));
+            if moved {
+                let time = data.areas.get(&session.chapter).map_or(session.time, |stats| stats.time_played);
+                reached.push((Split::Checkpoint(session.chapter.clone(), session.room.clone()), time));
+            }
+        }
+        for (split, time) in reached {
+            if self.checkpoint_times.contains_key(&split) && !self.undone.contains(&split) {
+                continue;
+            }
+            self.undone.remove(&split);
+            self.checkpoint_times.insert(split.clone(), time);
+            self.file_times.insert(split, data.file_time);
+        }
+        let previous_chapters = previous.time_map(TimingSource::TimePlayed);
+        for split in data.time_map(TimingSource::TimePlayed).into_keys() {
+            if !previous_chapters.contains_key(&split) {
+                self.file_times.insert(split, data.file_time);
+            }
+        }
+        for (chapter, stats) in &data.areas {
+            if previous.areas.get(chapter) != Some(stats) {
+                self.undone.remove(&Split::Chapter(chapter.clone()));
+            }
+        }
+    }
+
+    /// The time of each completed split of the route. The time of a split is the time since the
+    /// previous split in the same chapter, or since the start of the chapter if there is none.
+    /// Checkpoints are only timed when using the time played, since the best times of a chapter
+    /// are not split up by checkpoint. When using the file time, the time of a split is the
+    /// file time since the previous split instead, for the splits that were seen being reached
+    fn split_times(&self) -> Result<TimeMap> {
+        let mut times = self.raw_split_times()?;
+        times.retain(|split, _| !self.undone.contains(split) && !self.unchanged_since_reset(split));
+        Ok(times)
+    }
+
+    /// Whether a chapter has not been completed since the attempt was reset by hand. The save
+    /// does not say when a chapter was completed, so it counts once it was played since the reset
+    /// and the game has left it
+    fn unchanged_since_reset(&self, split: &Split) -> bool {
+        let (Some(attempt_start), Some(current_save), Split::Chapter(chapter)) = (self.attempt_start.as_ref(), self.current_save.as_ref(), split) else {
+            return false;
+        };
+        attempt_start.areas.get(chapter) == current_save.areas.get(chapter)
+            || current_save.current_session.as_ref().is_some_and(|session| &session.chapter == chapter)
+    }
+
+    fn raw_split_times(&self) -> Result<TimeMap> {
+        let mut times = self.chapter_split_times()?;
+        if self.timing_source == TimingSource::FileTime {
+            // Splits that were reached before rcas started have no file time, so they keep the
+            // time played
+            times.extend(self.file_split_times());
+        }
+        Ok(times)
+    }
+
+    /// The time of each completed split of the route according to the times of the chapters in
+    /// the save
+    fn chapter_split_times(&self) -> Result<TimeMap> {
+        let chapter_times = self.current_save.as_ref().ok_or(anyhow!("no current save!"))?.time_map(self.timing_source);
+        let mut times = HashMap::new();
+        // The time in each chapter at which the last split in that chapter happened
+        let mut chapter_offsets: HashMap<&Chapter, Duration> = HashMap::new();
+        // After a reset by hand, the time played before the reset belongs to earlier attempts
+        let time_before_reset = |chapter: &Chapter| match (self.timing_source, self.attempt_start.as_ref()) {
+            (TimingSource::TimePlayed | TimingSource::FileTime, Some(attempt_start)) => attempt_start.areas.get(chapter).map_or(Duration::ZERO, |stats| stats.time_played),
+            _ => Duration::ZERO,
+        };
+
+        for route_split in &self.route.splits {
+            let split = &route_split.split;
+            let time_in_chapter = match split {
+                Split::Chapter(_) => chapter_times.get(split),
+                Split::Checkpoint(..) if matches!(self.timing_source, TimingSource::TimePlayed | TimingSource::FileTime) => self.checkpoint_times.get(split),
+                Split::Checkpoint(..) => None,
+            };
+            if let Some(time_in_chapter) = time_in_chapter {
+                let offset = chapter_offsets.insert(split.chapter(), *time_in_chapter).unwrap_or_else(|| time_before_reset(split.chapter()));
+                times.insert(split.clone(), time_in_chapter.saturating_sub(offset));
+            }
+        }
+
+        Ok(times)
+    }
+
+    fn file_split_times(&self) -> TimeMap {
+        let mut times = HashMap::new();
+        // The splits before the first one with a file time were reached before rcas was watching
+        let mut previous_file_time = self.file_time_start;
+        for route_split in &self.route.splits {
+            if let Some(file_time) = self.file_times.get(&route_split.split) {
+                times.insert(route_split.split.clone(), file_time.saturating_sub(previous_file_time));
+                previous_file_time = *file_time;
+            }
+        }
+        times
+    }
+
+    /// Whether a recording is being replayed. Replays are for demos and trying things out, so
+    /// nothing that happens in them is written to the splits or the history
+    fn is_replay(&self) -> bool {
+        self.save_dir.is_none()
     }
 
     fn save_data(&self) -> Result<()> {
-        let pb_writer = File::create(PB_PATH)?;
+        if self.is_replay() {
+            return Ok(());
+        }
+        std::fs::create_dir_all(splits_path(&self.route, self.timing_source, ""))?;
+        let pb_writer = File::create(splits_path(&self.route, self.timing_source, PB_FILE))?;
         serde_json::to_writer(pb_writer, &self.pb)?;
-        let best_splits_writer = File::create(BEST_SPLITS_PATH)?;
+        let best_splits_writer = File::create(splits_path(&self.route, self.timing_source, BEST_SPLITS_FILE))?;
         serde_json::to_writer(best_splits_writer, &self.best_splits)?;
+        let pb_rta_writer = File::create(splits_path(&self.route, self.timing_source, PB_RTA_FILE))?;
+        serde_json::to_writer(pb_rta_writer, &self.pb_rta)?;
 
         Ok(())
     }
 
+    /// Starts watching the next save slot that has a save file
+    fn switch_slot(&mut self) -> Result<()> {
+        let save_dir = self.save_dir.as_ref().ok_or(anyhow!("there are no save slots while replaying a recording"))?;
+        let slots = saves::save_slots(save_dir)?;
+        let slot = *slots.iter().find(|slot| **slot > self.save_slot).or(slots.first()).ok_or(anyhow!("no save files found in {}", save_dir.display()))?;
+        let path = saves::slot_path(save_dir, slot);
+
+        // The splits that were seen being reached belong to the run on the old slot
+        self.transition(Transition::Reset)?;
+        self.run = Run::new(&self.route);
+        self.current_save = saves::load_save(&path).ok();
+        self.file_time_start = self.current_save.as_ref().map_or(Duration::ZERO, |save| save.file_time);
+        self.saved_at = None;
+        self.watcher = AsyncWatcher::new(&path, self.watch_options)?;
+        self.save_slot = slot;
+
+        self.terminal.lock().unwrap().clear()?;
+        self.mark_done_before_attempt()?;
+        self.terminal.lock().unwrap().write_status_default(&format!("watching save slot {}", slot))
+    }
+
+    /// Abandons the current attempt. Splits only count again once they are reached after this
+    fn reset_attempt(&mut self) -> Result<()> {
+        self.transition(Transition::Reset)?;
+        self.attempt_start = self.current_save.clone();
+        self.redraw()?;
+        self.terminal.lock().unwrap().write_status_default("the attempt was reset")
+    }
+
+    /// Takes back the last split of the route that was reached or skipped
+    fn undo_split(&mut self) -> Result<()> {
+        let Some(split) = self.run.last_done().cloned() else {
+            return self.terminal.lock().unwrap().write_status_default("there is no split to undo");
+        };
+        self.transition(Transition::Undo(split))?;
+        self.redraw()
+    }
+
+    /// Skips the first split of the route that has not been reached
+    fn skip_split(&mut self) -> Result<()> {
+        let Some(split) = self.run.next_split().cloned() else {
+            return self.terminal.lock().unwrap().write_status_default("there is no split left to skip");
+        };
+        self.transition(Transition::Skip(split))?;
+        if self.run.can_finish() {
+            self.transition(Transition::Finish)?;
+        }
+        self.redraw()
+    }
+
+    /// Switches to the next timing source, along with its PB and best splits
+    fn toggle_timing_source(&mut self) -> Result<()> {
+        self.save_data()?;
+        self.timing_source = self.timing_source.next();
+        migrate_legacy_splits(&self.terminal, &self.route, self.timing_source);
+        self.pb = load_time_map(&self.terminal, &splits_path(&self.route, self.timing_source, PB_FILE), "pb");
+        self.best_splits = load_time_map(&self.terminal, &splits_path(&self.route, self.timing_source, BEST_SPLITS_FILE), "best splits");
+        seed_golds(&mut self.best_splits, &self.pb);
+        // The golds and PB of the attempt were measured with the previous timing source
+        self.golds.clear();
+        self.previous_pb = None;
+        self.pb_rta = load_time_map(&self.terminal, &splits_path(&self.route, self.timing_source, PB_RTA_FILE), "pb real time");
+        self.history = load_history(&self.terminal, &splits_path(&self.route, self.timing_source, HISTORY_FILE));
+        self.redraw()?;
+        self.terminal.lock().unwrap().write_status_default(&format!("timing with {}", self.timing_source.name().to_lowercase()))
+    }
+
+    fn toggle_pause(&mut self) -> Result<()> {
+        self.paused = !self.paused;
+        if self.paused {
+            self.clock.pause();
+            return self.terminal.lock().unwrap().write_status("paused. save updates are held back until the timer is unpaused", Color::Yellow);
+        }
+        self.clock.resume();
+        for data in std::mem::take(&mut self.paused_saves) {
+            self.apply_save(data);
+        }
+        self.terminal.lock().unwrap().write_status_default("unpaused")
+    }
+
+    /// Clears the screen and draws the splits again
+    fn redraw(&self) -> Result<()> {
+        self.terminal.lock().unwrap().clear()?;
+        self.print_times()
+    }
+
+    /// Runs the action bound to a key. Returns whether the timer should quit
     fn handle_key(&mut self, keycode: KeyCode) -> bool {
-        match keycode {
-            KeyCode::Char('q') => return true,
-            _ => (),
+        let Some(action) = self.bindings.action(keycode) else {
+            return false;
+        };
+        let result = match action {
+            Action::Quit => return true,
+            Action::Reset => self.reset_attempt(),
+            Action::Undo => self.undo_split(),
+            Action::Skip => self.skip_split(),
+            Action::SwitchComparison => self.switch_comparison(),
+            Action::ToggleTimingSource => self.toggle_timing_source(),
+            Action::SaveSplits if self.is_replay() => self.terminal.lock().unwrap().write_status_default("splits are not saved while replaying"),
+            Action::SaveSplits => self.save_data().and_then(|_| self.terminal.lock().unwrap().write_status_default("saved splits")),
+            Action::Pause => self.toggle_pause(),
+            Action::SwitchSlot => self.switch_slot(),
+            Action::ToggleStats => {
+                self.show_stats = !self.show_stats;
+                self.redraw()
+            },
+            Action::Help => {
+                self.show_help = !self.show_help;
+                self.redraw()
+            },
+        };
+        if let Err(e) = result {
+            self.terminal.lock().unwrap().write_error(&format!("could not {}: {:#}", action.description(), e)).unwrap();
         }
         false
     }
 }
 
+/// Sends a message at a fixed interval, until the receiver is dropped
+fn ticker(interval: Duration) -> Receiver<()> {
+    let (mut tx, rx) = channel(1);
+    std::thread::spawn(move || {
+        while futures::executor::block_on(tx.send(())).is_ok() {
+            std::thread::sleep(interval);
+        }
+    });
+    rx
+}
+
+/// The path of a file that stores data for a specific route. PBs and best splits are kept
+/// separately for each route and timing source, since they are not comparable between them
+fn splits_path(route: &Route, timing_source: TimingSource, file: &str) -> PathBuf {
+    Path::new(SPLITS_DIR).join(route.id()).join(timing_source.id()).join(file)
+}
+
+/// Where older versions kept the splits, before they were stored per route and timing source. The
+/// time played was the only timing source back then
+fn legacy_splits_paths(route: &Route, file: &str) -> Vec<PathBuf> {
+    let mut paths = vec![Path::new(SPLITS_DIR).join(route.id()).join(file)];
+    // Before there were routes, the splits of Any% were kept in the working directory
+    if route.id() == Route::any_percent().id() {
+        paths.push(PathBuf::from(file));
+    }
+    paths
+}
+
+/// Moves the PB and best splits from where older versions kept them, unless there already are
+/// splits in the current place
+fn migrate_legacy_splits(terminal: &Mutex<Terminal>, route: &Route, timing_source: TimingSource) {
+    if timing_source != TimingSource::TimePlayed {
+        return;
+    }
+    for file in [PB_FILE, BEST_SPLITS_FILE] {
+        let path = splits_path(route, timing_source, file);
+        if path.exists() {
+            continue;
+        }
+        let Some(legacy) = legacy_splits_paths(route, file).into_iter().find(|legacy| legacy.is_file()) else {
+            continue;
+        };
+        let result = std::fs::create_dir_all(splits_path(route, timing_source, "")).and_then(|_| std::fs::rename(&legacy, &path));
+        let mut term = terminal.lock().unwrap();
+        match result {
+            Ok(()) => term.write_status_default(&format!("moved {} to {}", legacy.display(), path.display())).unwrap(),
+            Err(e) => term.write_error(&format!("could not move {} to {}: {}", legacy.display(), path.display(), e)).unwrap(),
+        }
+    }
+}
+
+/// Uses the PB time of every split that has no best split yet as its best split, since the best
+/// split can't be slower than it
+fn seed_golds(best_splits: &mut TimeMap, pb: &TimeMap) {
+    for (split, time) in pb {
+        best_splits.entry(split.clone()).or_insert(*time);
+    }
+}
+
+/// Loads the attempt history from a file, falling back to an empty history if it can't be loaded.
+/// New attempts are still appended to the file in that case
+fn load_history(terminal: &Mutex<Terminal>, path: &Path) -> History {
+    History::load(path).unwrap_or_else(|e| {
+        terminal.lock().unwrap().write_error(&format!("could not load attempt history: {:#}", e)).unwrap();
+        History::empty(path)
+    })
+}
+
+/// Loads a map of times from a file, falling back to an empty map if it can't be loaded
+fn load_time_map(terminal: &Mutex<Terminal>, path: &Path, name: &str) -> TimeMap {
+    if let Ok(reader) = File::open(path) {
+        serde_json::from_reader(reader).unwrap_or_else(|_| {
+            terminal.lock().unwrap().write_error(&format!("could not deserialize {} from file. initializing empty {}", name, name)).unwrap();
+            HashMap::new()
+        })
+    } else {
+        terminal.lock().unwrap().write_status_default(&format!("could not open {} file. initializing empty {}", name, name)).unwrap();
+        HashMap::new()
+    }
+}
+
 struct TimeTotals {
     total_time: Duration,
     pb_total: Duration,
-    pb_total_running: Duration,
+    /// The total of the active comparison, up to the last split that was reached
+    comparison_total_running: Duration,
+    /// The total of the best splits, if every split of the route has one
+    sum_of_best: Option<Duration>,
+    /// The current time with the best splits of the splits that are left added on, if they all
+    /// have one
+    best_possible_time: Option<Duration>,
 }
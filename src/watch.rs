@@ -1,62 +1,238 @@
-use std::{path::Path, sync::Mutex};
+use std::{path::{Path, PathBuf}, sync::{Arc, Mutex, Weak, atomic::{AtomicBool, Ordering}}, time::{Duration, Instant, SystemTime}};
 
-use anyhow::Result;
-use futures::{channel::mpsc::{channel, Receiver}, SinkExt};
+use anyhow::{anyhow, Result};
+use futures::{channel::mpsc::{channel, Receiver, Sender}, SinkExt};
 use notify::{event::{Event, ModifyKind}, RecommendedWatcher, Watcher, RecursiveMode, EventKind};
 
-use crate::saves::TimeMap;
+use crate::saves::SaveStats;
+
+/// How long to wait before each retry when the save can't be loaded
+const RETRY_DELAYS: [Duration; 4] = [
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(200),
+    Duration::from_millis(400),
+];
+
+/// Something that happened to the save file
+#[derive(Debug)]
+pub enum WatchEvent {
+    /// The save was changed
+    SaveUpdated(SaveStats),
+    /// The save was changed, but could not be loaded
+    ParseError(anyhow::Error),
+    /// The save can't be watched the way it was. Watching continues through polling
+    WatcherError(anyhow::Error),
+    /// The save file does not exist (anymore)
+    FileMissing(PathBuf),
+    /// Every update of the recording that is being replayed has been sent
+    ReplayFinished,
+}
+
+/// How the save file should be watched
+#[derive(Clone, Copy, Debug)]
+pub struct WatchOptions {
+    /// How often the save file is checked for changes when polling
+    pub poll_interval: Duration,
+    /// Always poll the save file instead of relying on the events from the file system
+    pub force_polling: bool,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            force_polling: false,
+        }
+    }
+}
 
 pub struct AsyncWatcher {
     // This needs to be on the struct, since it will otherwise go out of scope, and therefore stop
-    // watching
+    // watching. It is none when the file system can't be watched, in which case the file is polled
     #[allow(dead_code)]
-    watcher: RecommendedWatcher,
-    pub watcher_rx: Receiver<TimeMap>,
+    watcher: Option<RecommendedWatcher>,
+    // The polling thread stops once this is dropped
+    #[allow(dead_code)]
+    state: Option<Arc<WatchState>>,
+    pub watcher_rx: Receiver<WatchEvent>,
+}
+
+/// The state that is shared between the native watcher and the polling thread
+struct WatchState {
+    path: PathBuf,
+    tx: Mutex<Sender<WatchEvent>>,
+    last_save: Mutex<Option<SaveStats>>,
+    /// Whether the polling thread reports changes. This is turned on when the native watcher
+    /// fails, since it will then not report changes itself
+    polling: AtomicBool,
+    /// When the native watcher last reported a change to the save
+    last_event: Mutex<Option<Instant>>,
 }
 
 impl AsyncWatcher {
-    pub fn new(path: &Path) -> Result<Self> {
-        let (mut watcher, watcher_rx) = Self::create_watcher()?;
-
-        watcher.watch(path, RecursiveMode::NonRecursive)?;
-
-        Ok(Self { watcher, watcher_rx })
-    }
-
-    fn create_watcher() -> Result<(RecommendedWatcher, Receiver<TimeMap>)> {
-        let (mut tx, rx) = channel(1);
-        let last_save: Mutex<Option<TimeMap>> = Mutex::new(None);
-
-        let watcher = RecommendedWatcher::new(move |res: notify::Result<Event>| {
-            futures::executor::block_on(async {
-                if let Ok(event) = res {
-                    if let EventKind::Modify(kind) = event.kind {
-                        if let ModifyKind::Data(_) = kind {
-                            let path = event.paths.get(0).expect("could not get path for watcher");
-                            match crate::saves::load_save(path.as_ref()) {
-                                Ok(data) => {
-                                    let already_printed = if let Some(old_data) = last_save.lock().unwrap().as_ref() {
-                                        // We assume that a save file with more chapters completed
-                                        // is more recent than one with fewer completed
-                                        data.len() <= old_data.len()
-                                    } else {
-                                        false
-                                    };
-                                    if !already_printed {
-                                        if let Err(e) = tx.send(data.clone()).await {
-                                            println!("error sending save data from watcher: {:?}", e);
-                                        }
-                                        *last_save.lock().unwrap() = Some(data);
-                                    }
-                                },
-                                Err(e) => println!("unable to load save data: {:?}", e),
-                            }
+    pub fn new(path: &Path, options: WatchOptions) -> Result<Self> {
+        // Errors can be sent before anything is received, so there needs to be some room for them
+        let (tx, watcher_rx) = channel(16);
+        let state = Arc::new(WatchState {
+            path: path.to_owned(),
+            tx: Mutex::new(tx),
+            last_save: Mutex::new(None),
+            polling: AtomicBool::new(options.force_polling),
+            last_event: Mutex::new(None),
+        });
+
+        let watcher = if options.force_polling {
+            None
+        } else {
+            match Self::create_watcher(&state) {
+                Ok(watcher) => Some(watcher),
+                Err(e) => {
+                    state.send(WatchEvent::WatcherError(e.context("could not watch the save file, polling it instead")));
+                    state.polling.store(true, Ordering::Relaxed);
+                    None
+                },
+            }
+        };
+
+        let weak_state = Arc::downgrade(&state);
+        std::thread::spawn(move || poll(weak_state, options.poll_interval));
+
+        Ok(Self { watcher, state: Some(state), watcher_rx })
+    }
+
+    /// Replays a recording of save updates instead of watching the save. Also returns the save the
+    /// recording started from, if it has one
+    pub fn from_replay(path: &Path, speed: f64) -> Result<(Self, Option<SaveStats>)> {
+        let (tx, watcher_rx) = channel(16);
+        let initial = crate::record::replay(path, speed, tx)?;
+
+        Ok((Self { watcher: None, state: None, watcher_rx }, initial))
+    }
+
+    fn create_watcher(state: &Arc<WatchState>) -> Result<RecommendedWatcher> {
+        let weak_state = Arc::downgrade(state);
+
+        let mut watcher = RecommendedWatcher::new(move |res: notify::Result<Event>| {
+            let Some(state) = weak_state.upgrade() else {
+                return;
+            };
+            match res {
+                Ok(event) => {
+                    let changes_save = match event.kind {
+                        // The save can be replaced by writing a new file and renaming it over the
+                        // old one, so new files with the name of the save count as well. Removing
+                        // the save is reported as a missing file
+                        EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Name(_) | ModifyKind::Any) => event.paths.iter().any(|path| path == &state.path),
+                        _ => false,
+                    };
+                    if changes_save {
+                        *state.last_event.lock().unwrap() = Some(Instant::now());
+                        if !state.polling.load(Ordering::Relaxed) {
+                            state.load_and_send();
                         }
                     }
-                }
-            })
+                },
+                Err(e) => {
+                    if !state.polling.swap(true, Ordering::Relaxed) {
+                        state.send(WatchEvent::WatcherError(anyhow::Error::new(e).context("error while watching the save file, polling it instead")));
+                    }
+                },
+            }
         }, notify::Config::default())?;
 
-        Ok((watcher, rx))
+        // The directory is watched instead of the file itself, since the watch would otherwise be
+        // lost when the file is replaced
+        let dir = state.path.parent().ok_or(anyhow!("save file {} has no parent directory", state.path.display()))?;
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        Ok(watcher)
+    }
+}
+
+impl WatchState {
+    /// Loads the save, and sends it if it changed since the last one that was sent. A single
+    /// write of the save usually causes several events, which would otherwise all be sent
+    fn load_and_send(&self) {
+        match self.load_with_retries() {
+            Ok(data) => {
+                let already_printed = self.last_save.lock().unwrap().as_ref() == Some(&data);
+                if !already_printed {
+                    *self.last_save.lock().unwrap() = Some(data.clone());
+                    self.send(WatchEvent::SaveUpdated(data));
+                }
+            },
+            Err(_) if !self.path.exists() => self.send(WatchEvent::FileMissing(self.path.clone())),
+            Err(e) => self.send(WatchEvent::ParseError(e)),
+        }
+    }
+
+    /// Sends an event to the receiver. If the receiver is gone, nobody is interested in the event
+    /// anymore, so it is dropped
+    fn send(&self, event: WatchEvent) {
+        let _ = futures::executor::block_on(self.tx.lock().unwrap().send(event));
+    }
+
+    /// Loads the save, trying again a few times if it fails. The event for a change can arrive
+    /// while the game is still writing the save, in which case only part of it can be read
+    fn load_with_retries(&self) -> Result<SaveStats> {
+        let mut result = crate::saves::load_save(&self.path);
+        for delay in RETRY_DELAYS {
+            if result.is_ok() {
+                break;
+            }
+            std::thread::sleep(delay);
+            result = crate::saves::load_save(&self.path);
+        }
+        result
+    }
+}
+
+/// The modification time and size of a file, which together tell whether it was changed
+fn file_version(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Checks the save for changes until the watcher is dropped. While the native watcher works,
+/// changes are only used to check that it has not gone silent. If a change is not followed by an
+/// event from the native watcher within one interval, it is assumed to not work, and the changes
+/// are reported from here instead
+fn poll(state: Weak<WatchState>, interval: Duration) {
+    let mut version = state.upgrade().and_then(|state| file_version(&state.path));
+    // When a change was noticed that the native watcher has not reported yet
+    let mut unreported_change: Option<Instant> = None;
+
+    loop {
+        std::thread::sleep(interval);
+        let Some(state) = state.upgrade() else {
+            return;
+        };
+
+        let new_version = file_version(&state.path);
+        let changed = new_version != version;
+        version = new_version;
+
+        if state.polling.load(Ordering::Relaxed) {
+            if changed {
+                state.load_and_send();
+            }
+            continue;
+        }
+
+        if changed && unreported_change.is_none() {
+            // The native watcher may not have caught up yet, so it gets another interval
+            let now = Instant::now();
+            let reported = state.last_event.lock().unwrap().is_some_and(|last_event| now.duration_since(last_event) <= interval);
+            if !reported {
+                unreported_change = Some(now);
+            }
+        } else if let Some(changed_at) = unreported_change.take() {
+            let reported = state.last_event.lock().unwrap().is_some_and(|last_event| last_event >= changed_at);
+            if !reported {
+                state.send(WatchEvent::WatcherError(anyhow!("the save watcher is not reporting changes, polling the save file instead")));
+                state.polling.store(true, Ordering::Relaxed);
+                state.load_and_send();
+            }
+        }
     }
 }